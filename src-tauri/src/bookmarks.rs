@@ -0,0 +1,322 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{edit_journal::PositionShift, error::AppError};
+
+/// A named bookmark or a colored region of a file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: u64,
+    pub offset: u64,
+    /// The length of the region; zero for a bookmark of a single position.
+    pub length: u64,
+    pub label: String,
+    pub comment: String,
+    /// The color of the region as a CSS color value.
+    pub color: Option<String>,
+}
+
+/// The bookmarks of a file stored in the application configuration directory. The positions of
+/// the bookmarks follow the edits of the file; the stored positions always refer to the file on
+/// disk, so bookmarks moved by unsaved edits are stored when the file is saved.
+///
+/// The bookmarks are keyed by the path and the content identity of the file: bookmarks stored for
+/// other contents at the same path, such as a different file saved over it or a file which another
+/// process has changed or appended to, are not loaded.
+#[derive(Default, Serialize, Deserialize)]
+pub struct FileBookmarks {
+    /// The file the bookmarks belong to.
+    pub file_name: String,
+    /// The content identity of the file the bookmarks were stored for; see `content_identity`.
+    #[serde(default)]
+    identity: String,
+    next_id: u64,
+    pub bookmarks: Vec<Bookmark>,
+    /// Whether unsaved edits have moved the bookmarks away from the stored positions.
+    #[serde(skip)]
+    moved: bool,
+}
+
+impl FileBookmarks {
+    /// Loads the bookmarks of a file; a file without stored bookmarks for its contents has none.
+    ///
+    /// # Arguments
+    /// * `file_name` - the name of the file.
+    /// * `identity` - the content identity of the file; see `content_identity`.
+    pub fn load(file_name: &str, identity: String) -> Self {
+        let stored = bookmarks_path(file_name)
+            .filter(|path| path.exists())
+            .and_then(|path| confy::load_path::<FileBookmarks>(path).ok())
+            .filter(|stored| stored.identity == identity);
+
+        Self {
+            file_name: file_name.to_string(),
+            identity,
+            ..stored.unwrap_or_default()
+        }
+    }
+
+    /// Stores the bookmarks using confy. The file format is TOML.
+    fn store(&self) -> Result<(), AppError> {
+        match bookmarks_path(&self.file_name) {
+            Some(path) => confy::store_path(path, self).map_err(|e| AppError::Io(e.to_string())),
+            None => Err(AppError::NotFound(
+                "The configuration directory is not available".to_string(),
+            )),
+        }
+    }
+
+    /// Stores the bookmarks if their positions refer to the file on disk.
+    fn store_unless_moved(&self) -> Result<(), AppError> {
+        match self.moved {
+            true => Ok(()),
+            false => self.store(),
+        }
+    }
+
+    /// Moves the bookmarks along with the bytes they refer to after the file was edited.
+    ///
+    /// # Arguments
+    /// * `shifts` - the position changes of the edits in the order they were made.
+    pub fn shift(&mut self, shifts: &[PositionShift]) {
+        if shifts.is_empty() {
+            return;
+        }
+
+        for bookmark in &mut self.bookmarks {
+            let start = shifts
+                .iter()
+                .fold(bookmark.offset, |pos, s| s.move_start(pos));
+            if bookmark.length > 0 {
+                let end = bookmark.offset + bookmark.length;
+                let end = shifts.iter().fold(end, |end, s| s.move_end(end));
+                bookmark.length = end.saturating_sub(start);
+            }
+            bookmark.offset = start;
+        }
+
+        self.moved = true;
+    }
+
+    /// Loads the stored bookmarks again after the unsaved edits of the file were discarded.
+    pub fn discard_moves(&mut self) {
+        if self.moved {
+            *self = Self::load(&self.file_name, std::mem::take(&mut self.identity));
+        }
+    }
+
+    /// Stores the bookmarks after the file was saved into the specified file.
+    ///
+    /// # Arguments
+    /// * `file_name` - the name of the saved file.
+    /// * `identity` - the content identity of the saved file; see `content_identity`.
+    pub fn saved(&mut self, file_name: &str, identity: String) -> Result<(), AppError> {
+        self.file_name = file_name.to_string();
+        self.identity = identity;
+        match self.moved || !self.bookmarks.is_empty() {
+            true => {
+                self.moved = false;
+                self.store()
+            }
+            false => Ok(()),
+        }
+    }
+
+    /// Adds a bookmark and stores the bookmarks.
+    ///
+    /// # Arguments
+    /// * `offset` - the position of the bookmark.
+    /// * `length` - the length of the region; zero for a bookmark of a single position.
+    /// * `label` - the name of the bookmark.
+    /// * `comment` - a free-form comment.
+    /// * `color` - the color of the region as a CSS color value.
+    ///
+    /// # Returns
+    /// The added bookmark.
+    pub fn add(
+        &mut self,
+        offset: u64,
+        length: u64,
+        label: String,
+        comment: String,
+        color: Option<String>,
+    ) -> Result<Bookmark, AppError> {
+        self.change(|bookmarks| {
+            bookmarks.next_id += 1;
+            let bookmark = Bookmark {
+                id: bookmarks.next_id,
+                offset,
+                length,
+                label,
+                comment,
+                color,
+            };
+
+            let index = bookmarks.bookmarks.partition_point(|b| b.offset <= offset);
+            bookmarks.bookmarks.insert(index, bookmark.clone());
+            bookmark
+        })
+    }
+
+    /// Removes a bookmark and stores the bookmarks.
+    ///
+    /// # Arguments
+    /// * `id` - the identifier of the bookmark.
+    ///
+    /// # Returns
+    /// `true` if the bookmark existed; `false` otherwise.
+    pub fn remove(&mut self, id: u64) -> Result<bool, AppError> {
+        if self.get(id).is_none() {
+            return Ok(false);
+        }
+
+        self.change(|bookmarks| bookmarks.bookmarks.retain(|b| b.id != id))?;
+        Ok(true)
+    }
+
+    /// Changes the bookmarks and stores them. The change is rolled back if the bookmarks can't be
+    /// stored, so the bookmarks in memory always match the stored ones. Bookmarks moved by unsaved
+    /// edits are only stored when the file is saved.
+    ///
+    /// # Arguments
+    /// * `f` - the function to change the bookmarks with.
+    ///
+    /// # Returns
+    /// The result of the function or an error if storing the bookmarks failed.
+    fn change<T, F>(&mut self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let previous = (self.next_id, self.bookmarks.clone());
+        let result = f(self);
+        if let Err(e) = self.store_unless_moved() {
+            (self.next_id, self.bookmarks) = previous;
+            return Err(e);
+        }
+
+        Ok(result)
+    }
+
+    /// Gets a bookmark by its identifier.
+    pub fn get(&self, id: u64) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.id == id)
+    }
+}
+
+/// Gets the content identity of a file: the size and the BLAKE3 hash of the first bytes.
+///
+/// # Arguments
+/// * `size` - the size of the file.
+/// * `header` - the first bytes of the file.
+pub fn content_identity(size: u64, header: &[u8]) -> String {
+    format!("{}:{}", size, blake3::hash(header).to_hex())
+}
+
+/// Gets the path of the bookmarks of a file in the `bookmarks` directory next to the application
+/// config. The file is named after the BLAKE3 hash of the absolute path of the bookmarked file.
+fn bookmarks_path(file_name: &str) -> Option<PathBuf> {
+    let config = confy::get_configuration_file_path("hex_ff", None).ok()?;
+    let path = Path::new(file_name);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let key = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+
+    Some(
+        config
+            .parent()?
+            .join("bookmarks")
+            .join(format!("{}.toml", key)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit_journal::EditJournal;
+
+    /// Gets the positions and lengths of the bookmarks.
+    fn ranges(bookmarks: &FileBookmarks) -> Vec<(u64, u64)> {
+        bookmarks
+            .bookmarks
+            .iter()
+            .map(|b| (b.offset, b.length))
+            .collect()
+    }
+
+    #[test]
+    fn bookmarks_follow_the_edits() {
+        let mut journal = EditJournal::new(10);
+        let mut bookmarks = FileBookmarks::default();
+        for (id, (offset, length)) in [(4, 0), (6, 3)].into_iter().enumerate() {
+            bookmarks.bookmarks.push(Bookmark {
+                id: id as u64,
+                offset,
+                length,
+                label: String::new(),
+                comment: String::new(),
+                color: None,
+            });
+        }
+
+        // 0123456789 -> 01xy23456789
+        journal.insert(2, b"xy".to_vec()).unwrap();
+        bookmarks.shift(&journal.take_shifts());
+        assert_eq!(ranges(&bookmarks), vec![(6, 0), (8, 3)]);
+
+        // The region loses its first byte: 01xy234789
+        journal.delete(7, 2).unwrap();
+        bookmarks.shift(&journal.take_shifts());
+        assert_eq!(ranges(&bookmarks), vec![(6, 0), (7, 2)]);
+
+        // The bookmarked byte is replaced: ZZZ1xy23ZZZ789
+        journal.replace_ranges(&[(0, 1), (6, 1)], b"ZZZ").unwrap();
+        bookmarks.shift(&journal.take_shifts());
+        assert_eq!(ranges(&bookmarks), vec![(8, 0), (11, 2)]);
+
+        // Overwriting doesn't move any bytes.
+        journal.overwrite(0, b"abc".to_vec()).unwrap();
+        assert!(journal.take_shifts().is_empty());
+
+        // The deleted byte comes back in front of the region.
+        while journal.undo().is_some() {}
+        bookmarks.shift(&journal.take_shifts());
+        assert_eq!(ranges(&bookmarks), vec![(4, 0), (7, 2)]);
+
+        journal.redo();
+        bookmarks.shift(&journal.take_shifts());
+        assert_eq!(ranges(&bookmarks), vec![(6, 0), (9, 2)]);
+        assert!(bookmarks.moved);
+    }
+
+    #[test]
+    fn content_identity_covers_the_size_and_the_header() {
+        let identity = content_identity(10, b"header");
+
+        assert_eq!(identity, content_identity(10, b"header"));
+        assert_ne!(identity, content_identity(11, b"header"));
+        assert_ne!(identity, content_identity(10, b"Header"));
+    }
+}
@@ -0,0 +1,205 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, scan_file},
+    tasks::TaskContext,
+    types::AppState,
+};
+
+/// The event emitted with the results of a completed byte analysis task.
+pub const BYTE_ANALYSIS_EVENT: &str = "byte-analysis";
+
+/// The size of an entropy block unless requested otherwise.
+pub const DEFAULT_ENTROPY_BLOCK_SIZE: u64 = 4096;
+
+/// The maximum amount of entropy blocks; the block size is increased for larger files.
+const MAX_ENTROPY_BLOCKS: u64 = 1024 * 1024;
+
+/// The byte frequencies of a range of a file and the entropies of the blocks of the whole file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ByteAnalysis {
+    pub task_id: u64,
+    pub start: u64,
+    pub length: u64,
+    /// The amount of each byte value in the range.
+    pub histogram: Vec<u64>,
+    pub block_size: u64,
+    /// The Shannon entropy of each block in bits per byte from 0 to 8; the last block may be shorter.
+    pub entropy: Vec<f32>,
+}
+
+/// Calculates the Shannon entropy of a block in bits per byte.
+fn shannon_entropy(counts: &[u64; 256], total: u64) -> f32 {
+    let total = total as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    entropy as f32
+}
+
+/// Gets the block size used for the entropy series of a file.
+///
+/// # Arguments
+/// * `file_size` - the size of the file.
+/// * `block_size` - the requested block size.
+///
+/// # Returns
+/// The requested block size increased so the file has at most `MAX_ENTROPY_BLOCKS` blocks.
+fn entropy_block_size(file_size: u64, block_size: u64) -> u64 {
+    block_size
+        .max(1)
+        .max(file_size.div_ceil(MAX_ENTROPY_BLOCKS))
+}
+
+/// Calculates the byte histogram of a range of a file and the entropy series of the whole file
+/// in a single pass, emitting the results with the `byte-analysis` event unless the task is cancelled.
+///
+/// # Arguments
+/// * `context` - the context of the analysis task.
+/// * `file_index` - the index of the file.
+/// * `start` - the position of the histogram range.
+/// * `length` - the length of the histogram range; the range is limited to the file size.
+/// * `block_size` - the size of an entropy block.
+///
+/// # Returns
+/// `Ok(())` if the analysis completed or was cancelled; an error otherwise.
+pub fn analyze_bytes(
+    context: &TaskContext,
+    file_index: usize,
+    start: u64,
+    length: Option<u64>,
+    block_size: u64,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    let total = file_size(&app_state, file_index)?;
+    let end = match length {
+        Some(length) => total.min(start.saturating_add(length)),
+        None => total,
+    };
+
+    let block_size = entropy_block_size(total, block_size);
+    let mut histogram = [0u64; 256];
+    let mut block = [0u64; 256];
+    let mut block_fill = 0;
+    let mut entropy = Vec::with_capacity(total.div_ceil(block_size) as usize);
+
+    let completed = scan_file(
+        &app_state,
+        file_index,
+        0..total,
+        0,
+        context.cancel_flag(),
+        |chunk| {
+            let chunk_end = chunk.pos + chunk.length as u64;
+            if start < chunk_end && chunk.pos < end {
+                let from = start.saturating_sub(chunk.pos) as usize;
+                let to = (end.min(chunk_end) - chunk.pos) as usize;
+                for b in &chunk.data[from..to] {
+                    histogram[*b as usize] += 1;
+                }
+            }
+
+            for b in chunk.data {
+                block[*b as usize] += 1;
+                block_fill += 1;
+                if block_fill == block_size {
+                    entropy.push(shannon_entropy(&block, block_fill));
+                    block = [0; 256];
+                    block_fill = 0;
+                }
+            }
+
+            context.progress(chunk_end, total);
+            true
+        },
+    )?;
+
+    if !completed {
+        return Ok(());
+    }
+
+    if block_fill > 0 {
+        entropy.push(shannon_entropy(&block, block_fill));
+    }
+
+    let _ = context.app.emit(
+        BYTE_ANALYSIS_EVENT,
+        ByteAnalysis {
+            task_id: context.task_id,
+            start,
+            length: end.saturating_sub(start),
+            histogram: histogram.to_vec(),
+            block_size,
+            entropy,
+        },
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the byte values of the data.
+    fn counts(data: &[u8]) -> [u64; 256] {
+        let mut counts = [0; 256];
+        for b in data {
+            counts[*b as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn entropy_of_known_inputs() {
+        let entropy = |data: &[u8]| shannon_entropy(&counts(data), data.len() as u64);
+
+        assert_eq!(entropy(&[0x41; 100]), 0.0);
+        assert_eq!(entropy(b"abababab"), 1.0);
+        assert_eq!(entropy(b"abcdabcd"), 2.0);
+        assert_eq!(entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+        assert!((entropy(b"aaab") - 0.811_278).abs() < 1e-5);
+    }
+
+    #[test]
+    fn large_files_get_larger_blocks() {
+        assert_eq!(entropy_block_size(1000, DEFAULT_ENTROPY_BLOCK_SIZE), 4096);
+        assert_eq!(entropy_block_size(1000, 0), 1);
+        assert_eq!(
+            entropy_block_size(MAX_ENTROPY_BLOCKS * 8192 + 1, DEFAULT_ENTROPY_BLOCK_SIZE),
+            8193
+        );
+    }
+}
@@ -13,6 +13,30 @@ pub struct AppConfig {
     error: bool,
     /// An error message if one occurred.
     error_message: String,
+    /// The maximum amount of bytes the frontend can read from a file with a single call.
+    #[serde(default = "default_max_read_length")]
+    max_read_length: usize,
+    /// A value indicating whether regular files are memory-mapped for faster random access.
+    /// A mapped file truncated by another process can crash the application, so this is off by default.
+    #[serde(default)]
+    memory_map_files: bool,
+}
+
+/// The default maximum amount of bytes read from a file with a single call.
+fn default_max_read_length() -> usize {
+    65536
+}
+
+impl AppConfig {
+    /// Gets the maximum amount of bytes the frontend can read from a file with a single call.
+    pub fn max_read_length(&self) -> usize {
+        self.max_read_length.max(1)
+    }
+
+    /// Gets a value indicating whether regular files are memory-mapped.
+    pub fn memory_map_files(&self) -> bool {
+        self.memory_map_files
+    }
 }
 
 // The default value for the application configuration.
@@ -24,6 +48,8 @@ impl ::std::default::Default for AppConfig {
             error: false,
             error_message: "".to_string(),
             dark_mode: false,
+            max_read_length: default_max_read_length(),
+            memory_map_files: false,
         }
     }
 }
@@ -42,6 +68,8 @@ pub fn get_app_config() -> AppConfig {
                 locale: "en".to_string(),
                 save_window_state: false,
                 dark_mode: false,
+                max_read_length: default_max_read_length(),
+                memory_map_files: false,
             };
             result
         }
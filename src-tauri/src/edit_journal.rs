@@ -0,0 +1,602 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::{
+    error::AppError,
+    file_source::FileSource,
+    piece_table::{PieceChange, PieceTable},
+};
+use serde::{Deserialize, Serialize};
+
+/// An undoable edit step in the edit history of a file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EditHistoryEntry {
+    /// The description of the edit.
+    pub description: String,
+    /// The position in the file the edit starts at.
+    pub offset: u64,
+    /// The amount of bytes the edit affected.
+    pub length: u64,
+}
+
+/// The undo and redo history of a file.
+#[derive(Serialize, Deserialize)]
+pub struct EditHistory {
+    /// The steps which can be undone, the most recent one last.
+    pub undo: Vec<EditHistoryEntry>,
+    /// The steps which can be redone, the next one to redo last.
+    pub redo: Vec<EditHistoryEntry>,
+}
+
+/// A change of the byte positions of the edited view: the `removed` bytes at `offset` were
+/// replaced by `inserted` bytes, moving the bytes after them.
+#[derive(Clone, Copy)]
+pub struct PositionShift {
+    pub offset: u64,
+    pub removed: u64,
+    pub inserted: u64,
+}
+
+impl PositionShift {
+    /// Gets the shift which moves the positions back.
+    fn inverse(&self) -> Self {
+        Self {
+            offset: self.offset,
+            removed: self.inserted,
+            inserted: self.removed,
+        }
+    }
+
+    /// Moves the position of the first byte of a range; a range starting in the replaced bytes
+    /// starts at the replacement.
+    pub fn move_start(&self, pos: u64) -> u64 {
+        if pos < self.offset {
+            pos
+        } else if pos < self.offset + self.removed {
+            self.offset
+        } else {
+            pos - self.removed + self.inserted
+        }
+    }
+
+    /// Moves the exclusive end position of a range; a range ending in the replaced bytes ends
+    /// after the replacement.
+    pub fn move_end(&self, end: u64) -> u64 {
+        if end <= self.offset {
+            end
+        } else if end < self.offset + self.removed {
+            self.offset + self.inserted
+        } else {
+            end - self.removed + self.inserted
+        }
+    }
+}
+
+/// A group of document changes undone and redone as a single step.
+struct EditStep {
+    entry: EditHistoryEntry,
+    changes: Vec<PieceChange>,
+    /// The position changes of the step in the order they were made.
+    shifts: Vec<PositionShift>,
+}
+
+/// The range of bytes typed into the most recent edit step.
+struct TypedRange {
+    offset: u64,
+    length: u64,
+    insert: bool,
+}
+
+/// The edit journal of an open file. The edits are made into a piece table document so
+/// the file on disk is never touched; reads are resolved through the document so the
+/// frontend always sees the edited view of the file.
+pub struct EditJournal {
+    document: PieceTable,
+    undo_steps: Vec<EditStep>,
+    redo_steps: Vec<EditStep>,
+    typed: Option<TypedRange>,
+    shifts: Vec<PositionShift>,
+}
+
+impl EditJournal {
+    /// Creates a new empty edit journal.
+    ///
+    /// # Arguments
+    /// * `original_size` - the size of the file on disk.
+    pub fn new(original_size: u64) -> Self {
+        Self {
+            document: PieceTable::new(original_size),
+            undo_steps: Vec::new(),
+            redo_steps: Vec::new(),
+            typed: None,
+            shifts: Vec::new(),
+        }
+    }
+
+    /// Gets a value indicating whether the journal contains any edits.
+    pub fn is_modified(&self) -> bool {
+        !self.undo_steps.is_empty()
+    }
+
+    /// Gets the size of the edited view of the file.
+    pub fn size(&self) -> u64 {
+        self.document.size()
+    }
+
+    /// Overwrites bytes in the edited view. Single bytes typed next to each other
+    /// are grouped into the previous undo step.
+    ///
+    /// # Arguments
+    /// * `offset` - the position of the first byte to overwrite.
+    /// * `data` - the new bytes.
+    ///
+    /// # Returns
+    /// The size of the edited view or an error if the range is outside of the file.
+    pub fn overwrite(&mut self, offset: u64, data: Vec<u8>) -> Result<u64, AppError> {
+        let end = offset.checked_add(data.len() as u64);
+        if data.is_empty() || end.is_none_or(|end| end > self.size()) {
+            return Err(AppError::OutOfRange);
+        }
+
+        let change = self.document.replace(offset, data.len() as u64, &data);
+        self.push_change("Overwrite", offset, &data, false, change);
+
+        Ok(self.size())
+    }
+
+    /// Inserts bytes into the edited view. Single bytes typed next to each other
+    /// are grouped into the previous undo step.
+    ///
+    /// # Arguments
+    /// * `offset` - the position to insert the bytes at; the size of the file appends the bytes.
+    /// * `data` - the bytes to insert.
+    ///
+    /// # Returns
+    /// The size of the edited view or an error if the position is outside of the file.
+    pub fn insert(&mut self, offset: u64, data: Vec<u8>) -> Result<u64, AppError> {
+        if data.is_empty() || offset > self.size() {
+            return Err(AppError::OutOfRange);
+        }
+
+        let change = self.document.replace(offset, 0, &data);
+        self.push_change("Insert", offset, &data, true, change);
+
+        Ok(self.size())
+    }
+
+    /// Deletes bytes from the edited view.
+    ///
+    /// # Arguments
+    /// * `offset` - the position of the first byte to delete.
+    /// * `length` - the amount of bytes to delete.
+    ///
+    /// # Returns
+    /// The size of the edited view or an error if the range is outside of the file.
+    pub fn delete(&mut self, offset: u64, length: u64) -> Result<u64, AppError> {
+        let end = offset.checked_add(length);
+        if length == 0 || end.is_none_or(|end| end > self.size()) {
+            return Err(AppError::OutOfRange);
+        }
+
+        let change = self.document.replace(offset, length, &[]);
+        let shift = PositionShift {
+            offset,
+            removed: length,
+            inserted: 0,
+        };
+        self.push_step("Delete", offset, length, vec![change], vec![shift]);
+
+        Ok(self.size())
+    }
+
+    /// Replaces multiple ranges of the edited view with the same bytes as a single undo step.
+    ///
+    /// # Arguments
+    /// * `ranges` - the positions and lengths of the ranges in ascending order; the ranges must not overlap.
+    /// * `data` - the bytes to replace each range with.
+    ///
+    /// # Returns
+    /// The size of the edited view or an error if a range is outside of the file or the ranges overlap.
+    pub fn replace_ranges(&mut self, ranges: &[(u64, u64)], data: &[u8]) -> Result<u64, AppError> {
+        let mut previous_end = 0;
+        for (offset, length) in ranges {
+            let end = offset
+                .checked_add(*length)
+                .filter(|end| *offset >= previous_end && *end <= self.size())
+                .ok_or(AppError::OutOfRange)?;
+            previous_end = end;
+        }
+
+        let (first, last_end) = match (ranges.first(), ranges.last()) {
+            (Some(first), Some(last)) => (first.0, last.0 + last.1),
+            _ => return Ok(self.size()),
+        };
+
+        // Each shift is relative to the positions moved by the previous ones.
+        let mut removed = 0;
+        let mut inserted = 0;
+        let mut shifts = Vec::new();
+        for (offset, length) in ranges.iter().filter(|r| r.1 != data.len() as u64) {
+            shifts.push(PositionShift {
+                offset: offset - removed + inserted,
+                removed: *length,
+                inserted: data.len() as u64,
+            });
+            removed += length;
+            inserted += data.len() as u64;
+        }
+
+        // The bytes after the last range are only moved, so the end of the last replacement
+        // is found from their length.
+        let tail_length = self.size() - last_end;
+        let change = self.document.replace_ranges(ranges, data);
+        let last_end = self.size() - tail_length;
+        self.push_step("Replace", first, last_end - first, vec![change], shifts);
+
+        Ok(self.size())
+    }
+
+    /// Undoes the most recent edit step.
+    ///
+    /// # Returns
+    /// The undone step or `None` if there was nothing to undo.
+    pub fn undo(&mut self) -> Option<EditHistoryEntry> {
+        self.typed = None;
+
+        let step = self.undo_steps.pop()?;
+        step.changes
+            .iter()
+            .rev()
+            .for_each(|change| self.document.revert(change));
+        self.shifts
+            .extend(step.shifts.iter().rev().map(PositionShift::inverse));
+
+        let entry = step.entry.clone();
+        self.redo_steps.push(step);
+        Some(entry)
+    }
+
+    /// Redoes the most recently undone edit step.
+    ///
+    /// # Returns
+    /// The redone step or `None` if there was nothing to redo.
+    pub fn redo(&mut self) -> Option<EditHistoryEntry> {
+        self.typed = None;
+
+        let step = self.redo_steps.pop()?;
+        step.changes
+            .iter()
+            .for_each(|change| self.document.apply(change));
+        self.shifts.extend(step.shifts.iter().copied());
+
+        let entry = step.entry.clone();
+        self.undo_steps.push(step);
+        Some(entry)
+    }
+
+    /// Takes the position changes made by the edits, undos and redos since the last call, in the
+    /// order they were made, so positions into the edited view such as bookmarks can follow them.
+    pub fn take_shifts(&mut self) -> Vec<PositionShift> {
+        std::mem::take(&mut self.shifts)
+    }
+
+    /// Gets the undo and redo history of the journal.
+    pub fn history(&self) -> EditHistory {
+        EditHistory {
+            undo: self.undo_steps.iter().map(|s| s.entry.clone()).collect(),
+            redo: self.redo_steps.iter().map(|s| s.entry.clone()).collect(),
+        }
+    }
+
+    /// Reads bytes from the edited view of the file.
+    ///
+    /// # Arguments
+    /// * `file` - the underlying file on disk.
+    /// * `pos` - the position in the edited view to start reading from.
+    /// * `buffer` - the buffer to read the bytes into.
+    ///
+    /// # Returns
+    /// The amount of bytes read; this is less than the buffer length at the end of the file.
+    pub fn read(
+        &self,
+        file: &mut FileSource,
+        pos: u64,
+        buffer: &mut [u8],
+    ) -> std::io::Result<usize> {
+        self.document.read(file, pos, buffer)
+    }
+
+    /// Records a new undo step. A new step discards the redo history.
+    fn push_step(
+        &mut self,
+        description: &str,
+        offset: u64,
+        length: u64,
+        changes: Vec<PieceChange>,
+        shifts: Vec<PositionShift>,
+    ) {
+        self.typed = None;
+        self.redo_steps.clear();
+        self.shifts.extend(shifts.iter().copied());
+        self.undo_steps.push(EditStep {
+            entry: EditHistoryEntry {
+                description: description.to_string(),
+                offset,
+                length,
+            },
+            changes,
+            shifts,
+        });
+    }
+
+    /// Records a written change. A single typed byte continues the previous step if that
+    /// was typed as well and the byte is written inside or directly after it.
+    fn push_change(
+        &mut self,
+        description: &str,
+        offset: u64,
+        data: &[u8],
+        insert: bool,
+        change: PieceChange,
+    ) {
+        // Bytes typed over bytes just inserted keep the step in insert mode.
+        let typed_insert = match &self.typed {
+            Some(typed) if data.len() == 1 => {
+                let end = typed.offset + typed.length;
+                if offset >= typed.offset && offset < end && !insert {
+                    Some(typed.insert)
+                } else if offset == end && insert == typed.insert {
+                    Some(insert)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let shifts = match insert {
+            true => vec![PositionShift {
+                offset,
+                removed: 0,
+                inserted: data.len() as u64,
+            }],
+            false => Vec::new(),
+        };
+        match (typed_insert, self.undo_steps.last_mut()) {
+            (Some(_), Some(step)) => {
+                step.changes.push(change);
+                step.shifts.extend(shifts.iter().copied());
+                step.entry.length = step.entry.length.max(offset + 1 - step.entry.offset);
+                self.shifts.extend(shifts);
+            }
+            _ => self.push_step(description, offset, data.len() as u64, vec![change], shifts),
+        }
+
+        let insert = typed_insert.unwrap_or(insert);
+        if let Some(step) = self.undo_steps.last() {
+            self.typed = match data.len() {
+                1 => Some(TypedRange {
+                    offset: step.entry.offset,
+                    length: step.entry.length,
+                    insert,
+                }),
+                _ => None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a file source over a file with the specified contents in the temporary directory.
+    fn temp_source(name: &str, data: &[u8]) -> FileSource {
+        let path = std::env::temp_dir().join(format!("hexej-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        FileSource::new(std::fs::File::open(path).unwrap(), false)
+    }
+
+    /// Reads the whole edited view.
+    fn contents(journal: &EditJournal, file: &mut FileSource) -> Vec<u8> {
+        let mut buffer = vec![0; journal.size() as usize];
+        assert_eq!(journal.read(file, 0, &mut buffer).unwrap(), buffer.len());
+        buffer
+    }
+
+    /// Gets the amount of undo and redo steps.
+    fn step_counts(journal: &EditJournal) -> (usize, usize) {
+        let history = journal.history();
+        (history.undo.len(), history.redo.len())
+    }
+
+    #[test]
+    fn overwrite_continues_at_the_end_of_the_step() {
+        let mut file = temp_source("overwrite", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        journal.overwrite(1, vec![b'X']).unwrap();
+        journal.overwrite(2, vec![b'Y']).unwrap();
+        // Going back inside the typed bytes continues the step as well.
+        journal.overwrite(1, vec![b'Z']).unwrap();
+        journal.overwrite(3, vec![b'W']).unwrap();
+
+        assert_eq!(contents(&journal, &mut file), b"aZYWef");
+        assert_eq!(step_counts(&journal), (1, 0));
+        let entry = &journal.history().undo[0];
+        assert_eq!((entry.offset, entry.length), (1, 3));
+
+        journal.undo();
+        assert_eq!(contents(&journal, &mut file), b"abcdef");
+        assert_eq!(step_counts(&journal), (0, 1));
+    }
+
+    #[test]
+    fn typing_over_inserted_bytes_keeps_insert_mode() {
+        let mut file = temp_source("insert-over", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        journal.insert(2, vec![b'X']).unwrap();
+        journal.insert(3, vec![b'Y']).unwrap();
+        journal.overwrite(3, vec![b'Z']).unwrap();
+        // The step is still in insert mode, so an insert after it continues the step.
+        journal.insert(4, vec![b'W']).unwrap();
+
+        assert_eq!(contents(&journal, &mut file), b"abXZWcdef");
+        assert_eq!(step_counts(&journal), (1, 0));
+
+        journal.undo();
+        assert_eq!(contents(&journal, &mut file), b"abcdef");
+        journal.redo();
+        assert_eq!(contents(&journal, &mut file), b"abXZWcdef");
+    }
+
+    #[test]
+    fn switching_between_insert_and_overwrite_starts_a_new_step() {
+        let mut file = temp_source("switch", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        journal.overwrite(1, vec![b'X']).unwrap();
+        journal.insert(2, vec![b'Y']).unwrap();
+        journal.overwrite(4, vec![b'Z']).unwrap();
+
+        assert_eq!(contents(&journal, &mut file), b"aXYcZef");
+        assert_eq!(step_counts(&journal), (3, 0));
+
+        journal.undo();
+        assert_eq!(contents(&journal, &mut file), b"aXYcdef");
+        journal.undo();
+        assert_eq!(contents(&journal, &mut file), b"aXcdef");
+    }
+
+    #[test]
+    fn other_edits_end_the_typed_step() {
+        let mut file = temp_source("reset", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        journal.insert(0, vec![b'X']).unwrap();
+        journal.delete(3, 1).unwrap();
+        journal.insert(1, vec![b'Y']).unwrap();
+        assert_eq!(contents(&journal, &mut file), b"XYabdef");
+        assert_eq!(step_counts(&journal), (3, 0));
+
+        journal.replace_ranges(&[(5, 1)], b"EE").unwrap();
+        journal.insert(2, vec![b'Z']).unwrap();
+        assert_eq!(contents(&journal, &mut file), b"XYZabdEEf");
+        assert_eq!(step_counts(&journal), (5, 0));
+
+        journal.undo();
+        journal.redo();
+        journal.insert(3, vec![b'W']).unwrap();
+        assert_eq!(contents(&journal, &mut file), b"XYZWabdEEf");
+        assert_eq!(step_counts(&journal), (6, 0));
+
+        journal.undo();
+        journal.insert(3, vec![b'V']).unwrap();
+        assert_eq!(contents(&journal, &mut file), b"XYZVabdEEf");
+        // A new step discards the redo history.
+        assert_eq!(step_counts(&journal), (6, 0));
+
+        while journal.undo().is_some() {}
+        assert_eq!(contents(&journal, &mut file), b"abcdef");
+        assert_eq!(step_counts(&journal), (0, 6));
+    }
+
+    #[test]
+    fn edits_are_checked_against_the_edited_size() {
+        let mut file = temp_source("ranges", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        // Inserting at the size of the file appends; one past it is outside of the file.
+        assert_eq!(journal.insert(6, vec![b'g']).ok(), Some(7));
+        assert_eq!(
+            journal.insert(8, vec![b'h']).err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.insert(0, Vec::new()).err(),
+            Some(AppError::OutOfRange)
+        );
+
+        assert_eq!(journal.overwrite(5, vec![b'X', b'Y']).ok(), Some(7));
+        assert_eq!(
+            journal.overwrite(6, vec![b'X', b'Y']).err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.overwrite(7, vec![b'X']).err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.overwrite(u64::MAX, vec![b'X', b'Y']).err(),
+            Some(AppError::OutOfRange)
+        );
+
+        assert_eq!(journal.delete(3, 0).err(), Some(AppError::OutOfRange));
+        assert_eq!(journal.delete(7, 1).err(), Some(AppError::OutOfRange));
+        assert_eq!(
+            journal.delete(1, u64::MAX).err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(journal.delete(6, 1).ok(), Some(6));
+
+        assert_eq!(contents(&journal, &mut file), b"abcdeX");
+        assert_eq!(journal.history().undo.len(), 3);
+    }
+
+    #[test]
+    fn replaced_ranges_must_be_ordered_and_inside_the_file() {
+        let mut file = temp_source("replace-ranges", b"abcdef");
+        let mut journal = EditJournal::new(6);
+
+        assert_eq!(
+            journal.replace_ranges(&[(2, 2), (3, 1)], b"X").err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.replace_ranges(&[(4, 1), (1, 1)], b"X").err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.replace_ranges(&[(5, 2)], b"X").err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            journal.replace_ranges(&[(1, u64::MAX)], b"X").err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(journal.replace_ranges(&[], b"X").ok(), Some(6));
+        assert!(!journal.is_modified());
+
+        // Adjacent ranges and a range ending at the end of the file are valid.
+        assert_eq!(
+            journal
+                .replace_ranges(&[(0, 1), (1, 2), (5, 1)], b"XY")
+                .ok(),
+            Some(8)
+        );
+        assert_eq!(contents(&journal, &mut file), b"XYXYdeXY");
+        let entry = &journal.history().undo[0];
+        assert_eq!((entry.offset, entry.length), (0, 8));
+    }
+}
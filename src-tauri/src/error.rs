@@ -0,0 +1,133 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::{fmt, io, sync::PoisonError};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// The error returned by the backend commands. The error is serialized to the frontend as an object
+/// with the `kind` of the error for localisation and an English `message`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppError {
+    /// The file or directory doesn't exist.
+    NotFound(String),
+    /// The access to the file or directory was denied.
+    PermissionDenied(String),
+    /// The file index doesn't refer to an open file.
+    InvalidIndex,
+    /// The position or the range is outside the file.
+    OutOfRange,
+    /// The file is opened as read-only.
+    ReadOnly,
+    /// The file is being saved.
+    Busy,
+    /// The file was changed while the command was running.
+    Conflict,
+    /// An argument of the command is invalid.
+    InvalidArgument(String),
+    /// The data of the file doesn't match the expected structure.
+    InvalidData(String),
+    /// The structure template is invalid.
+    InvalidTemplate(String),
+    /// An I/O operation failed.
+    Io(String),
+    /// A thread panicked while holding the application state lock.
+    LockPoisoned,
+    /// A background operation failed to run.
+    Task(String),
+}
+
+impl AppError {
+    /// Gets the kind of the error as sent to the frontend.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::InvalidIndex => "invalid_index",
+            AppError::OutOfRange => "out_of_range",
+            AppError::ReadOnly => "read_only",
+            AppError::Busy => "busy",
+            AppError::Conflict => "conflict",
+            AppError::InvalidArgument(_) => "invalid_argument",
+            AppError::InvalidData(_) => "invalid_data",
+            AppError::InvalidTemplate(_) => "invalid_template",
+            AppError::Io(_) => "io",
+            AppError::LockPoisoned => "lock_poisoned",
+            AppError::Task(_) => "task",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message)
+            | AppError::PermissionDenied(message)
+            | AppError::InvalidArgument(message)
+            | AppError::InvalidData(message)
+            | AppError::InvalidTemplate(message)
+            | AppError::Io(message)
+            | AppError::Task(message) => write!(f, "{}", message),
+            AppError::InvalidIndex => write!(f, "Invalid file index"),
+            AppError::OutOfRange => write!(f, "Invalid file position"),
+            AppError::ReadOnly => write!(f, "The file is opened as read-only"),
+            AppError::Busy => write!(f, "The file is being saved"),
+            AppError::Conflict => write!(f, "The file was changed during the operation"),
+            AppError::LockPoisoned => write!(f, "The application state is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 2)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => AppError::NotFound(e.to_string()),
+            io::ErrorKind::PermissionDenied => AppError::PermissionDenied(e.to_string()),
+            _ => AppError::Io(e.to_string()),
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    fn from(_: PoisonError<T>) -> Self {
+        AppError::LockPoisoned
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Task(e.to_string())
+    }
+}
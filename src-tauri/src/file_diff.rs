@@ -0,0 +1,715 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::atomic::Ordering};
+use tauri::{Emitter, Manager};
+
+use crate::{error::AppError, file_scan::read_file_data, tasks::TaskContext, types::AppState};
+
+/// The event emitted with the differing ranges found by a completed comparison task.
+pub const DIFF_RESULTS_EVENT: &str = "diff-results";
+
+/// The maximum amount of bytes compared at a time.
+const COMPARE_CHUNK_SIZE: usize = 1024 * 1024;
+/// The amount of bytes compared at a time after a difference; doubled while the files are equal.
+const MIN_COMPARE_SIZE: usize = 4096;
+/// The amount of equal bytes required for the files to be in sync again after a difference.
+const SYNC_LENGTH: usize = 16;
+/// The maximum amount of bytes skipped in either file while looking for the files to sync again.
+const RESYNC_WINDOW: usize = 64 * 1024;
+/// The amount of bytes skipped in either file tried before the whole resync window is indexed.
+const NEAR_SKIP: usize = 64;
+
+/// How two files are compared.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMode {
+    /// The bytes at the same positions are compared.
+    Bytes,
+    /// Inserted and deleted bytes are detected so the rest of the files stays aligned.
+    Insertions,
+}
+
+/// A range differing between two files. An inserted or deleted range has the length of zero
+/// in the file it is missing from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiffRange {
+    pub offset_a: u64,
+    pub length_a: u64,
+    pub offset_b: u64,
+    pub length_b: u64,
+}
+
+/// The results of a comparison task.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiffResults {
+    pub task_id: u64,
+    pub ranges: Vec<DiffRange>,
+}
+
+/// A comparison in the insertions mode which can be continued from where it stopped. The last
+/// comparison is kept in the application state, so finding the next or the previous difference
+/// continues it instead of comparing the files from the start again.
+pub struct InsertionDiff {
+    /// The indices of the compared files.
+    files: (usize, usize),
+    /// The revisions of the views of the files the comparison is valid for.
+    revisions: (u64, u64),
+    /// The position in the first file the comparison continues from.
+    pos_a: u64,
+    /// The position in the second file the comparison continues from.
+    pos_b: u64,
+    /// The differing ranges found so far.
+    ranges: Vec<DiffRange>,
+}
+
+impl InsertionDiff {
+    /// Gets the first range found starting after a position of the first file.
+    fn next_range(&self, offset: u64) -> Option<&DiffRange> {
+        let index = self.ranges.partition_point(|r| r.offset_a <= offset);
+        self.ranges.get(index)
+    }
+
+    /// Gets a value indicating whether a range may still grow as the comparison continues.
+    fn is_open(&self, range: &DiffRange) -> bool {
+        range.offset_a + range.length_a == self.pos_a
+            && range.offset_b + range.length_b == self.pos_b
+    }
+}
+
+/// Two open files being compared.
+struct FilePair<'a> {
+    app_state: &'a AppState,
+    task: Option<&'a TaskContext>,
+    file_a: usize,
+    file_b: usize,
+    size_a: u64,
+    size_b: u64,
+    revisions: (u64, u64),
+}
+
+impl<'a> FilePair<'a> {
+    fn new(
+        app_state: &'a AppState,
+        task: Option<&'a TaskContext>,
+        file_a: usize,
+        file_b: usize,
+    ) -> Result<Self, AppError> {
+        let state = |file_index| {
+            app_state.with_file(file_index, |file_state| {
+                Ok((file_state.file_size, file_state.revision))
+            })
+        };
+        let (size_a, revision_a) = state(file_a)?;
+        let (size_b, revision_b) = state(file_b)?;
+
+        Ok(Self {
+            app_state,
+            task,
+            file_a,
+            file_b,
+            size_a,
+            size_b,
+            revisions: (revision_a, revision_b),
+        })
+    }
+
+    /// Gets a value indicating whether the comparison task has been cancelled.
+    fn cancelled(&self) -> bool {
+        self.task
+            .is_some_and(|task| task.cancel_flag().load(Ordering::Relaxed))
+    }
+
+    /// Emits the progress of the comparison task.
+    fn progress(&self, position: u64, total: u64) {
+        if let Some(task) = self.task {
+            task.progress(position, total);
+        }
+    }
+
+    /// Takes the cached comparison in the insertions mode if it is of these files and their views
+    /// haven't changed since; otherwise a new comparison is started.
+    fn take_insertion_diff(&self) -> Result<InsertionDiff, AppError> {
+        let cached = self.app_state.diff_cache.lock()?.take();
+        Ok(match cached {
+            Some(diff)
+                if diff.files == (self.file_a, self.file_b) && diff.revisions == self.revisions =>
+            {
+                diff
+            }
+            _ => InsertionDiff {
+                files: (self.file_a, self.file_b),
+                revisions: self.revisions,
+                pos_a: 0,
+                pos_b: 0,
+                ranges: Vec::new(),
+            },
+        })
+    }
+
+    /// Stores a comparison in the insertions mode to be continued later.
+    fn store_insertion_diff(&self, diff: InsertionDiff) -> Result<(), AppError> {
+        *self.app_state.diff_cache.lock()? = Some(diff);
+        Ok(())
+    }
+
+    /// Reads the same range of both files; the range must be inside both files.
+    fn read_both(
+        &self,
+        pos: u64,
+        buffer_a: &mut [u8],
+        buffer_b: &mut [u8],
+    ) -> Result<(), AppError> {
+        read_file_data(self.app_state, self.file_a, pos, buffer_a)?;
+        read_file_data(self.app_state, self.file_b, pos, buffer_b)?;
+        Ok(())
+    }
+
+    /// Creates a range of the same positions of both files limited to the file sizes.
+    fn range(&self, start: u64, end: u64) -> DiffRange {
+        DiffRange {
+            offset_a: start,
+            length_a: end.min(self.size_a).saturating_sub(start),
+            offset_b: start,
+            length_b: end.min(self.size_b).saturating_sub(start),
+        }
+    }
+
+    /// Compares the bytes at the same positions; the tail of the longer file is one range.
+    fn diff_bytes(&self, max_results: usize) -> Result<Vec<DiffRange>, AppError> {
+        let common = self.size_a.min(self.size_b);
+        let mut buffer_a = vec![0; COMPARE_CHUNK_SIZE];
+        let mut buffer_b = vec![0; COMPARE_CHUNK_SIZE];
+        let mut ranges = Vec::new();
+        let mut pos = 0;
+
+        while pos < common && ranges.len() <= max_results && !self.cancelled() {
+            let count = COMPARE_CHUNK_SIZE.min((common - pos) as usize);
+            self.read_both(pos, &mut buffer_a[..count], &mut buffer_b[..count])?;
+
+            for i in 0..count {
+                if buffer_a[i] != buffer_b[i] {
+                    push_range(&mut ranges, self.range(pos + i as u64, pos + i as u64 + 1));
+                }
+            }
+
+            pos += count as u64;
+            self.progress(pos, common);
+        }
+
+        if pos == common && common < self.size_a.max(self.size_b) {
+            push_range(
+                &mut ranges,
+                self.range(common, self.size_a.max(self.size_b)),
+            );
+        }
+
+        ranges.truncate(max_results);
+        Ok(ranges)
+    }
+
+    /// Compares the files detecting inserted and deleted bytes. After each difference the
+    /// nearest positions where both files continue with the same bytes are looked for within
+    /// a window; differences longer than the window are reported as replaced windows.
+    ///
+    /// # Arguments
+    /// * `diff` - the comparison to continue.
+    /// * `stop` - checked before each step; returning `true` stops the comparison.
+    fn diff_insertions<F>(&self, diff: &mut InsertionDiff, stop: F) -> Result<(), AppError>
+    where
+        F: Fn(&InsertionDiff) -> bool,
+    {
+        let mut buffer_a = vec![0; COMPARE_CHUNK_SIZE.max(RESYNC_WINDOW + SYNC_LENGTH)];
+        let mut buffer_b = vec![0; buffer_a.len()];
+        let mut compare_size = MIN_COMPARE_SIZE;
+        let mut reported = diff.pos_a / COMPARE_CHUNK_SIZE as u64;
+
+        while (diff.pos_a < self.size_a || diff.pos_b < self.size_b)
+            && !stop(diff)
+            && !self.cancelled()
+        {
+            let count_a = read_file_data(
+                self.app_state,
+                self.file_a,
+                diff.pos_a,
+                &mut buffer_a[..compare_size],
+            )?;
+            let count_b = read_file_data(
+                self.app_state,
+                self.file_b,
+                diff.pos_b,
+                &mut buffer_b[..compare_size],
+            )?;
+
+            if count_a == 0 && count_b == 0 {
+                // Both files were truncated by another process during the comparison.
+                break;
+            }
+
+            let same = buffer_a[..count_a]
+                .iter()
+                .zip(&buffer_b[..count_b])
+                .take_while(|(a, b)| a == b)
+                .count();
+            diff.pos_a += same as u64;
+            diff.pos_b += same as u64;
+
+            if diff.pos_a / COMPARE_CHUNK_SIZE as u64 > reported {
+                reported = diff.pos_a / COMPARE_CHUNK_SIZE as u64;
+                self.progress(diff.pos_a, self.size_a);
+            }
+
+            if same == count_a && count_a == count_b {
+                compare_size = (compare_size * 2).min(COMPARE_CHUNK_SIZE);
+                continue;
+            }
+
+            compare_size = MIN_COMPARE_SIZE;
+            let (skip_a, skip_b) =
+                self.resync(diff.pos_a, diff.pos_b, &mut buffer_a, &mut buffer_b)?;
+            push_range(
+                &mut diff.ranges,
+                DiffRange {
+                    offset_a: diff.pos_a,
+                    length_a: skip_a as u64,
+                    offset_b: diff.pos_b,
+                    length_b: skip_b as u64,
+                },
+            );
+
+            diff.pos_a += skip_a as u64;
+            diff.pos_b += skip_b as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Finds the next or the previous range of bytes differing in the insertions mode. The
+    /// cached comparison of the files is only continued as far as needed for the range.
+    fn seek_insertion_difference(
+        &self,
+        offset: u64,
+        backward: bool,
+    ) -> Result<Option<DiffRange>, AppError> {
+        let mut diff = self.take_insertion_diff()?;
+
+        let range = match backward {
+            true => {
+                // The ranges ending before the offset can't grow once the offset is passed.
+                self.diff_insertions(&mut diff, |diff| diff.pos_a > offset)?;
+                diff.ranges
+                    .iter()
+                    .rev()
+                    .find(|r| r.offset_a < offset && r.offset_a + r.length_a <= offset)
+                    .copied()
+            }
+            false => {
+                self.diff_insertions(&mut diff, |diff| {
+                    diff.next_range(offset).is_some_and(|r| !diff.is_open(r))
+                })?;
+                diff.next_range(offset).copied()
+            }
+        };
+
+        self.store_insertion_diff(diff)?;
+        Ok(range)
+    }
+
+    /// Finds the amounts of bytes to skip in both files at a difference to have them in sync again.
+    fn resync(
+        &self,
+        pos_a: u64,
+        pos_b: u64,
+        buffer_a: &mut [u8],
+        buffer_b: &mut [u8],
+    ) -> Result<(usize, usize), AppError> {
+        let mut counts = (0, 0);
+        for window in [NEAR_SKIP, RESYNC_WINDOW] {
+            let length = window + SYNC_LENGTH;
+            let count_a =
+                read_file_data(self.app_state, self.file_a, pos_a, &mut buffer_a[..length])?;
+            let count_b =
+                read_file_data(self.app_state, self.file_b, pos_b, &mut buffer_b[..length])?;
+            let window_a = SyncWindow {
+                data: &buffer_a[..count_a],
+                at_end: pos_a + count_a as u64 == self.size_a,
+            };
+            let window_b = SyncWindow {
+                data: &buffer_b[..count_b],
+                at_end: pos_b + count_b as u64 == self.size_b,
+            };
+
+            let skips = match window {
+                NEAR_SKIP => near_sync(&window_a, &window_b),
+                _ => indexed_sync(&window_a, &window_b),
+            };
+
+            if let Some(skips) = skips {
+                return Ok(skips);
+            }
+
+            counts = (count_a, count_b);
+        }
+
+        Ok((counts.0.min(RESYNC_WINDOW), counts.1.min(RESYNC_WINDOW)))
+    }
+
+    /// Finds the first position at or after `pos` where the bytes differ or are equal.
+    /// The positions past the end of the shorter file differ.
+    fn find_forward(&self, pos: u64, differ: bool) -> Result<Option<u64>, AppError> {
+        let common = self.size_a.min(self.size_b);
+        let mut buffer_a = vec![0; COMPARE_CHUNK_SIZE];
+        let mut buffer_b = vec![0; COMPARE_CHUNK_SIZE];
+        let mut pos = pos;
+
+        while pos < common {
+            let count = COMPARE_CHUNK_SIZE.min((common - pos) as usize);
+            self.read_both(pos, &mut buffer_a[..count], &mut buffer_b[..count])?;
+
+            if let Some(i) = (0..count).find(|i| (buffer_a[*i] != buffer_b[*i]) == differ) {
+                return Ok(Some(pos + i as u64));
+            }
+
+            pos += count as u64;
+        }
+
+        match differ && pos < self.size_a.max(self.size_b) {
+            true => Ok(Some(pos)),
+            false => Ok(None),
+        }
+    }
+
+    /// Finds the last position before `pos` where the bytes differ or are equal.
+    /// The positions past the end of the shorter file differ.
+    fn find_backward(&self, pos: u64, differ: bool) -> Result<Option<u64>, AppError> {
+        let common = self.size_a.min(self.size_b);
+        let mut pos = pos.min(self.size_a.max(self.size_b));
+
+        if pos > common {
+            match differ {
+                true => return Ok(Some(pos - 1)),
+                false => pos = common,
+            }
+        }
+
+        let mut buffer_a = vec![0; COMPARE_CHUNK_SIZE];
+        let mut buffer_b = vec![0; COMPARE_CHUNK_SIZE];
+
+        while pos > 0 {
+            let start = pos.saturating_sub(COMPARE_CHUNK_SIZE as u64);
+            let count = (pos - start) as usize;
+            self.read_both(start, &mut buffer_a[..count], &mut buffer_b[..count])?;
+
+            if let Some(i) = (0..count).rfind(|i| (buffer_a[*i] != buffer_b[*i]) == differ) {
+                return Ok(Some(start + i as u64));
+            }
+
+            pos = start;
+        }
+
+        Ok(None)
+    }
+
+    /// Finds the next or the previous range of bytes differing at the same positions.
+    fn seek_byte_difference(
+        &self,
+        offset: u64,
+        backward: bool,
+    ) -> Result<Option<DiffRange>, AppError> {
+        let end = self.size_a.max(self.size_b);
+        let offset = offset.min(end);
+        let in_range = offset < end && self.find_forward(offset, true)? == Some(offset);
+
+        if !backward {
+            // The range the offset is in is skipped.
+            let from = match in_range {
+                true => match self.find_forward(offset, false)? {
+                    Some(pos) => pos,
+                    None => return Ok(None),
+                },
+                false => offset,
+            };
+
+            let start = match self.find_forward(from, true)? {
+                Some(pos) => pos,
+                None => return Ok(None),
+            };
+
+            let range_end = self.find_forward(start, false)?.unwrap_or(end);
+            return Ok(Some(self.range(start, range_end)));
+        }
+
+        let to = match in_range {
+            true => self.find_backward(offset, false)?.map_or(0, |pos| pos + 1),
+            false => offset,
+        };
+
+        let last = match self.find_backward(to, true)? {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+
+        let start = self.find_backward(last, false)?.map_or(0, |pos| pos + 1);
+        Ok(Some(self.range(start, last + 1)))
+    }
+}
+
+/// The bytes of a file following a difference.
+struct SyncWindow<'a> {
+    data: &'a [u8],
+    /// Whether the data reaches the end of the file.
+    at_end: bool,
+}
+
+/// Checks whether the files continue with the same bytes after skipping bytes in both.
+fn is_synced(a: &SyncWindow, b: &SyncWindow, skip_a: usize, skip_b: usize) -> bool {
+    let (tail_a, tail_b) = (&a.data[skip_a..], &b.data[skip_b..]);
+    match tail_a.len() >= SYNC_LENGTH && tail_b.len() >= SYNC_LENGTH {
+        true => tail_a[..SYNC_LENGTH] == tail_b[..SYNC_LENGTH],
+        // Near the end of the files the rest of both files must be equal.
+        false => a.at_end && b.at_end && tail_a == tail_b,
+    }
+}
+
+/// Tries the skips of up to `NEAR_SKIP` bytes in the order of the total amount of skipped bytes.
+fn near_sync(a: &SyncWindow, b: &SyncWindow) -> Option<(usize, usize)> {
+    for total in 1..=2 * NEAR_SKIP {
+        for skip_a in total.saturating_sub(NEAR_SKIP)..=total.min(NEAR_SKIP) {
+            let skip_b = total - skip_a;
+            if skip_a <= a.data.len() && skip_b <= b.data.len() && is_synced(a, b, skip_a, skip_b) {
+                return Some((skip_a, skip_b));
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the skips with the least total amount of skipped bytes by indexing the sync length
+/// sequences of the second window.
+fn indexed_sync(a: &SyncWindow, b: &SyncWindow) -> Option<(usize, usize)> {
+    let mut positions: HashMap<&[u8], usize> = HashMap::new();
+    for skip_b in 0..(b.data.len() + 1).saturating_sub(SYNC_LENGTH) {
+        positions
+            .entry(&b.data[skip_b..skip_b + SYNC_LENGTH])
+            .or_insert(skip_b);
+    }
+
+    let mut best: Option<(usize, usize)> = None;
+    for skip_a in 0..(a.data.len() + 1).saturating_sub(SYNC_LENGTH) {
+        if best.is_some_and(|(best_a, best_b)| skip_a >= best_a + best_b) {
+            break;
+        }
+
+        if let Some(skip_b) = positions.get(&a.data[skip_a..skip_a + SYNC_LENGTH]) {
+            if best.is_none_or(|(best_a, best_b)| skip_a + skip_b < best_a + best_b) {
+                best = Some((skip_a, *skip_b));
+            }
+        }
+    }
+
+    best
+}
+
+/// Adds a range to the list of differences, joining it with the previous range if they are adjacent.
+fn push_range(ranges: &mut Vec<DiffRange>, range: DiffRange) {
+    if let Some(last) = ranges.last_mut() {
+        if last.offset_a + last.length_a == range.offset_a
+            && last.offset_b + last.length_b == range.offset_b
+        {
+            last.length_a += range.length_a;
+            last.length_b += range.length_b;
+            return;
+        }
+    }
+
+    ranges.push(range);
+}
+
+/// Compares two open files, emitting the differing ranges with the `diff-results` event unless
+/// the task is cancelled. A comparison in the insertions mode continues the cached comparison of
+/// the files.
+///
+/// # Arguments
+/// * `context` - the context of the comparison task.
+/// * `file_a` - the index of the first file.
+/// * `file_b` - the index of the second file.
+/// * `mode` - how the files are compared.
+/// * `max_results` - the maximum amount of differing ranges to report.
+///
+/// # Returns
+/// `Ok(())` if the comparison completed or was cancelled; an error otherwise.
+pub fn compare_files(
+    context: &TaskContext,
+    file_a: usize,
+    file_b: usize,
+    mode: DiffMode,
+    max_results: usize,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    let files = FilePair::new(&app_state, Some(context), file_a, file_b)?;
+    let ranges = match mode {
+        DiffMode::Bytes => files.diff_bytes(max_results)?,
+        DiffMode::Insertions => {
+            let mut diff = files.take_insertion_diff()?;
+            files.diff_insertions(&mut diff, |diff| diff.ranges.len() > max_results)?;
+            let ranges = diff.ranges.iter().take(max_results).copied().collect();
+            files.store_insertion_diff(diff)?;
+            ranges
+        }
+    };
+
+    if context.cancel_flag().load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let _ = context.app.emit(
+        DIFF_RESULTS_EVENT,
+        DiffResults {
+            task_id: context.task_id,
+            ranges,
+        },
+    );
+
+    Ok(())
+}
+
+/// Finds the next or the previous differing range from a position. The range the position is
+/// in is skipped. In the insertions mode the position is a position of the first file.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_a` - the index of the first file.
+/// * `file_b` - the index of the second file.
+/// * `mode` - how the files are compared.
+/// * `offset` - the position to start from.
+/// * `backward` - whether to find the previous difference instead of the next one.
+///
+/// # Returns
+/// The differing range or `None` if there are no more differences in the direction.
+pub fn seek_difference(
+    app_state: &AppState,
+    file_a: usize,
+    file_b: usize,
+    mode: DiffMode,
+    offset: u64,
+    backward: bool,
+) -> Result<Option<DiffRange>, AppError> {
+    let files = FilePair::new(app_state, None, file_a, file_b)?;
+    match mode {
+        DiffMode::Bytes => files.seek_byte_difference(offset, backward),
+        DiffMode::Insertions => files.seek_insertion_difference(offset, backward),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo-random contents which don't repeat within the resync window.
+    fn contents(length: usize) -> Vec<u8> {
+        let mut seed = 0x2545_f491u32;
+        (0..length)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as u8
+            })
+            .collect()
+    }
+
+    /// Opens two files with the specified contents in the temporary directory.
+    fn open_pair(name: &str, data_a: &[u8], data_b: &[u8]) -> AppState {
+        let app_state = AppState::default();
+        for (suffix, data) in [("a", data_a), ("b", data_b)] {
+            let path = std::env::temp_dir().join(format!(
+                "hexfd-{}-{}-{}",
+                std::process::id(),
+                name,
+                suffix
+            ));
+            std::fs::write(&path, data).unwrap();
+            crate::open_file_state(path.to_string_lossy().to_string(), false, &app_state).unwrap();
+        }
+        app_state
+    }
+
+    /// The first file with five bytes inserted at 8000 and a byte changed at 15000.
+    fn edited(data: &[u8]) -> Vec<u8> {
+        let mut edited = data.to_vec();
+        edited[15000] ^= 0xff;
+        edited.splice(8000..8000, *b"\x00\x01\x02\x03\x04");
+        edited
+    }
+
+    const EXPECTED: [DiffRange; 2] = [
+        DiffRange {
+            offset_a: 8000,
+            length_a: 0,
+            offset_b: 8000,
+            length_b: 5,
+        },
+        DiffRange {
+            offset_a: 15000,
+            length_a: 1,
+            offset_b: 15005,
+            length_b: 1,
+        },
+    ];
+
+    #[test]
+    fn insertions_are_resynced() {
+        let data = contents(20000);
+        let app_state = open_pair("resync", &data, &edited(&data));
+
+        let files = FilePair::new(&app_state, None, 0, 1).unwrap();
+        let mut diff = files.take_insertion_diff().unwrap();
+        files.diff_insertions(&mut diff, |_| false).unwrap();
+        assert_eq!(diff.ranges, EXPECTED);
+        assert_eq!((diff.pos_a, diff.pos_b), (20000, 20005));
+    }
+
+    #[test]
+    fn seeking_continues_the_cached_comparison() {
+        let data = contents(20000);
+        let app_state = open_pair("seek", &data, &edited(&data));
+        let seek = |offset, backward| {
+            seek_difference(&app_state, 0, 1, DiffMode::Insertions, offset, backward).unwrap()
+        };
+
+        assert_eq!(seek(0, false), Some(EXPECTED[0]));
+        // Only the part of the files needed for the first range has been compared.
+        let pos_a = app_state.diff_cache.lock().unwrap().as_ref().unwrap().pos_a;
+        assert!(pos_a > 8000 && pos_a < 15000);
+
+        assert_eq!(seek(8000, false), Some(EXPECTED[1]));
+        assert_eq!(seek(15000, false), None);
+        assert_eq!(seek(15000, true), Some(EXPECTED[0]));
+        assert_eq!(seek(8000, true), None);
+
+        // Seeking backward without a cached comparison compares up to the offset.
+        *app_state.diff_cache.lock().unwrap() = None;
+        assert_eq!(seek(19000, true), Some(EXPECTED[1]));
+    }
+}
@@ -0,0 +1,244 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crc::{Crc, CRC_16_ARC, CRC_32_ISO_HDLC};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use tauri::{Emitter, Manager};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, scan_file},
+    tasks::TaskContext,
+    types::AppState,
+};
+
+/// The event emitted with the results of a completed hash calculation task.
+pub const HASH_RESULTS_EVENT: &str = "hash-results";
+
+static CRC_16: Crc<u16> = Crc::<u16>::new(&CRC_16_ARC);
+static CRC_32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// The supported hash and checksum algorithms.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// CRC-16/ARC.
+    Crc16,
+    /// CRC-32/ISO-HDLC as used by ZIP and PNG.
+    Crc32,
+    Adler32,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+/// A calculated hash as a lowercase hexadecimal string.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HashResult {
+    pub algorithm: HashAlgorithm,
+    pub value: String,
+}
+
+/// The results of a hash calculation task.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HashResults {
+    pub task_id: u64,
+    pub start: u64,
+    pub length: u64,
+    pub hashes: Vec<HashResult>,
+}
+
+/// The running state of a hash algorithm.
+enum Hasher {
+    Crc16(crc::Digest<'static, u16>),
+    Crc32(crc::Digest<'static, u32>),
+    Adler32(adler2::Adler32),
+    Md5(md5::Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Crc16 => Hasher::Crc16(CRC_16.digest()),
+            HashAlgorithm::Crc32 => Hasher::Crc32(CRC_32.digest()),
+            HashAlgorithm::Adler32 => Hasher::Adler32(adler2::Adler32::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc16(digest) => digest.update(data),
+            Hasher::Crc32(digest) => digest.update(data),
+            Hasher::Adler32(adler) => adler.write_slice(data),
+            Hasher::Md5(digest) => digest.update(data),
+            Hasher::Sha1(digest) => digest.update(data),
+            Hasher::Sha256(digest) => digest.update(data),
+            Hasher::Sha512(digest) => digest.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// Gets the hash as a lowercase hexadecimal string.
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Crc16(digest) => format!("{:04x}", digest.finalize()),
+            Hasher::Crc32(digest) => format!("{:08x}", digest.finalize()),
+            Hasher::Adler32(adler) => format!("{:08x}", adler.checksum()),
+            Hasher::Md5(digest) => to_hex(&digest.finalize()),
+            Hasher::Sha1(digest) => to_hex(&digest.finalize()),
+            Hasher::Sha256(digest) => to_hex(&digest.finalize()),
+            Hasher::Sha512(digest) => to_hex(&digest.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Calculates hashes over a range of a file in a single pass, emitting the results with the
+/// `hash-results` event unless the task is cancelled.
+///
+/// # Arguments
+/// * `context` - the context of the hash calculation task.
+/// * `file_index` - the index of the file.
+/// * `algorithms` - the algorithms to calculate.
+/// * `start` - the position of the range.
+/// * `length` - the length of the range; the range is limited to the file size.
+///
+/// # Returns
+/// `Ok(())` if the calculation completed or was cancelled; an error otherwise.
+pub fn hash_range(
+    context: &TaskContext,
+    file_index: usize,
+    algorithms: &[HashAlgorithm],
+    start: u64,
+    length: Option<u64>,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    let end = match length {
+        Some(length) => file_size(&app_state, file_index)?.min(start.saturating_add(length)),
+        None => file_size(&app_state, file_index)?,
+    };
+
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|a| Hasher::new(*a)).collect();
+    let mut hashed = 0;
+    let completed = scan_file(
+        &app_state,
+        file_index,
+        start..end,
+        0,
+        context.cancel_flag(),
+        |chunk| {
+            for hasher in &mut hashers {
+                hasher.update(chunk.data);
+            }
+
+            hashed += chunk.length as u64;
+            context.progress(chunk.pos + chunk.length as u64 - start, end - start);
+            true
+        },
+    )?;
+
+    if !completed {
+        return Ok(());
+    }
+
+    let hashes = algorithms
+        .iter()
+        .zip(hashers)
+        .map(|(algorithm, hasher)| HashResult {
+            algorithm: *algorithm,
+            value: hasher.finalize(),
+        })
+        .collect();
+
+    let _ = context.app.emit(
+        HASH_RESULTS_EVENT,
+        HashResults {
+            task_id: context.task_id,
+            start,
+            length: hashed,
+            hashes,
+        },
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Calculates a hash of the data fed to the hasher in two parts.
+    fn hash(algorithm: HashAlgorithm, data: &[u8]) -> String {
+        let mut hasher = Hasher::new(algorithm);
+        let (first, second) = data.split_at(data.len() / 2);
+        hasher.update(first);
+        hasher.update(second);
+        hasher.finalize()
+    }
+
+    #[test]
+    fn hashes_match_the_check_values() {
+        let data = b"123456789";
+        assert_eq!(hash(HashAlgorithm::Crc16, data), "bb3d");
+        assert_eq!(hash(HashAlgorithm::Crc32, data), "cbf43926");
+        assert_eq!(hash(HashAlgorithm::Adler32, data), "091e01de");
+        assert_eq!(
+            hash(HashAlgorithm::Md5, data),
+            "25f9e794323b453885f5181f1b624d0b"
+        );
+        assert_eq!(
+            hash(HashAlgorithm::Sha256, data),
+            "15e2b0d3c33891ebb0f1ef609ec419420c20e320ce94c65fbc8c3312448eb225"
+        );
+
+        let data = b"abc";
+        assert_eq!(
+            hash(HashAlgorithm::Sha1, data),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hash(HashAlgorithm::Blake3, b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::{
+    fs::OpenOptions,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bookmarks::content_identity,
+    edit_journal::EditJournal,
+    error::AppError,
+    file_scan::read_file_data,
+    file_watch::DiskState,
+    types::{AppState, DEFAULT_READ_LENGTH},
+};
+
+/// The size of the chunks the edited view is copied to disk with.
+const SAVE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Writes the edited view of an open file into the specified file.
+/// The data is first written into a temporary file in the same directory which is then
+/// renamed over the target, so the target is either the old or the new file even if
+/// the application crashes during the save. The file list is locked only while a chunk is
+/// read so the frontend can keep reading files during a long save; the file can't be edited
+/// or reloaded until the save has finished.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the open file to save.
+/// * `file_name` - the name of the file to write into.
+/// * `rw` - whether the saved file should be reopened for reading and writing.
+///
+/// # Returns
+/// `Ok(())` if the file was saved successfully; an error otherwise.
+pub fn save_file_state(
+    app_state: &AppState,
+    file_index: usize,
+    file_name: &str,
+    rw: bool,
+) -> Result<(), AppError> {
+    app_state.with_file(file_index, |file_state| match file_state.saving {
+        true => Err(AppError::Busy),
+        false => {
+            file_state.saving = true;
+            Ok(())
+        }
+    })?;
+
+    let result = save_view(app_state, file_index, file_name, rw);
+
+    // The file may have been closed during the save.
+    let _ = app_state.with_file(file_index, |file_state| {
+        file_state.saving = false;
+        Ok(())
+    });
+
+    result
+}
+
+/// Writes the edited view into a temporary file and replaces the target with it.
+fn save_view(
+    app_state: &AppState,
+    file_index: usize,
+    file_name: &str,
+    rw: bool,
+) -> Result<(), AppError> {
+    let target = Path::new(file_name);
+    let temp_path = temp_file_path(target);
+
+    let result = write_temp_file(app_state, file_index, target, &temp_path).and_then(|_| {
+        app_state.with_file(file_index, |file_state| {
+            // A mapped file can't be replaced on every platform.
+            file_state.file.unmap();
+            std::fs::rename(&temp_path, target)?;
+
+            // The old handle still refers to the replaced file, so reopen the saved one.
+            let mut file = OpenOptions::new().read(true).write(rw).open(target)?;
+            let metadata = file.metadata()?;
+            let file_size = metadata.len();
+            let mut header = vec![0; DEFAULT_READ_LENGTH];
+            let count = file.read(&mut header)?;
+            header.truncate(count);
+
+            file_state.file.replace_file(file);
+            file_state.file_name = file_name.to_string();
+            file_state.read_write = rw;
+            file_state.file_size = file_size;
+            file_state.journal = EditJournal::new(file_size);
+            file_state.revision += 1;
+            file_state.disk_state = Some(DiskState::from_metadata(&metadata));
+
+            // The bookmarks follow the saved file.
+            file_state
+                .bookmarks
+                .saved(file_name, content_identity(file_size, &header))
+        })
+    });
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Copies the edited view into the temporary file chunk by chunk and flushes it to disk.
+fn write_temp_file(
+    app_state: &AppState,
+    file_index: usize,
+    target: &Path,
+    temp_path: &Path,
+) -> Result<(), AppError> {
+    let mut temp_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(temp_path)?;
+
+    let size = app_state.with_file(file_index, |file_state| Ok(file_state.journal.size()))?;
+    let mut buffer = vec![0; SAVE_CHUNK_SIZE];
+    let mut pos = 0;
+    while pos < size {
+        let count = read_file_data(app_state, file_index, pos, &mut buffer)?;
+        if count == 0 {
+            break;
+        }
+
+        temp_file.write_all(&buffer[..count])?;
+        pos += count as u64;
+    }
+
+    if pos != size {
+        return Err(AppError::Io(
+            "The file was truncated by another process".to_string(),
+        ));
+    }
+
+    // Keep the permissions of the file being replaced.
+    if let Ok(metadata) = std::fs::metadata(target) {
+        temp_file.set_permissions(metadata.permissions())?;
+    }
+
+    Ok(temp_file.sync_all()?)
+}
+
+/// Gets the name of the temporary file used while saving the specified file.
+fn temp_file_path(target: &Path) -> PathBuf {
+    let file_name = match target.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => String::new(),
+    };
+
+    target.with_file_name(format!(".{}.hexff-save", file_name))
+}
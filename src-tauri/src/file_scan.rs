@@ -0,0 +1,142 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{error::AppError, types::AppState};
+
+/// The amount of bytes read from a file at a time while scanning through it.
+pub const SCAN_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// A chunk of a file passed to the visitor of `scan_file`.
+pub struct ScanChunk<'a> {
+    /// The position of the chunk in the file.
+    pub pos: u64,
+    /// The bytes of the chunk followed by up to `overlap` bytes of the next chunk.
+    pub data: &'a [u8],
+    /// The amount of bytes in `data` belonging to this chunk; items starting past this
+    /// are found again at the start of the next chunk.
+    pub length: usize,
+}
+
+/// Reads a range of the edited view of an open file chunk by chunk. The file list is locked
+/// only while a chunk is read so the frontend can keep reading the file during a long scan.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file to scan.
+/// * `range` - the range of the file to scan; the range is limited to the file size.
+/// * `overlap` - the amount of bytes the chunks overlap so items spanning two chunks are found.
+/// * `cancel` - the cancellation flag checked between the chunks.
+/// * `visit` - the function called for each chunk; returning `false` stops the scan.
+///
+/// # Returns
+/// `true` if the whole range was scanned; `false` if the scan was cancelled or stopped.
+pub fn scan_file<F>(
+    app_state: &AppState,
+    file_index: usize,
+    range: Range<u64>,
+    overlap: usize,
+    cancel: &AtomicBool,
+    mut visit: F,
+) -> Result<bool, AppError>
+where
+    F: FnMut(ScanChunk) -> bool,
+{
+    let mut buffer = vec![0; SCAN_CHUNK_SIZE + overlap];
+    let mut pos = range.start;
+
+    while pos < range.end {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        let count = app_state.with_file(file_index, |file_state| {
+            let end = range.end.min(file_state.file_size);
+            if pos >= end {
+                return Ok(0);
+            }
+
+            let wanted = buffer.len().min((end - pos) as usize);
+            Ok(file_state
+                .journal
+                .read(&mut file_state.file, pos, &mut buffer[..wanted])?)
+        })?;
+
+        if count == 0 {
+            break;
+        }
+
+        let length = count.min(SCAN_CHUNK_SIZE);
+        let chunk = ScanChunk {
+            pos,
+            data: &buffer[..count],
+            length,
+        };
+
+        if !visit(chunk) {
+            return Ok(false);
+        }
+
+        pos += length as u64;
+    }
+
+    Ok(true)
+}
+
+/// Reads bytes from the edited view of an open file.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file.
+/// * `pos` - the position to start reading from.
+/// * `buffer` - the buffer to read the bytes into.
+///
+/// # Returns
+/// The amount of bytes read; this is less than the buffer length at the end of the file.
+pub fn read_file_data(
+    app_state: &AppState,
+    file_index: usize,
+    pos: u64,
+    buffer: &mut [u8],
+) -> Result<usize, AppError> {
+    app_state.with_file(file_index, |file_state| {
+        Ok(file_state.journal.read(&mut file_state.file, pos, buffer)?)
+    })
+}
+
+/// Gets the current size of the edited view of an open file.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file.
+///
+/// # Returns
+/// The size of the file.
+pub fn file_size(app_state: &AppState, file_index: usize) -> Result<u64, AppError> {
+    app_state.with_file(file_index, |file_state| Ok(file_state.file_size))
+}
@@ -0,0 +1,122 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
+
+/// The original bytes of an open file. Regular files can optionally be memory-mapped so random
+/// access doesn't need a system call per read; pipes, devices and other files which can't be
+/// mapped, and all files when mapping is off, are read with seek and read instead.
+pub struct FileSource {
+    file: File,
+    map: Option<Mmap>,
+    memory_map: bool,
+}
+
+impl FileSource {
+    /// Creates a new file source.
+    ///
+    /// # Arguments
+    /// * `file` - the opened file.
+    /// * `memory_map` - whether the file is memory-mapped if possible.
+    pub fn new(file: File, memory_map: bool) -> Self {
+        let map = match memory_map {
+            true => map_file(&file),
+            false => None,
+        };
+
+        Self {
+            file,
+            map,
+            memory_map,
+        }
+    }
+
+    /// Switches to a new handle of the file, for example after the file was saved or reloaded.
+    /// The file is mapped again if mapping is on.
+    ///
+    /// # Arguments
+    /// * `file` - the opened file.
+    pub fn replace_file(&mut self, file: File) {
+        *self = Self::new(file, self.memory_map);
+    }
+
+    /// Gets the current size of the file on disk.
+    pub fn file_size(&self) -> std::io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    /// Releases the memory map; reads fall back to seek and read until the file is mapped again.
+    pub fn unmap(&mut self) {
+        self.map = None;
+    }
+
+    /// Reads bytes from the specified position. Reading stops at the end of the file, which is
+    /// before the end of the buffer if another process has truncated the file.
+    ///
+    /// # Arguments
+    /// * `pos` - the position in the file to start reading from.
+    /// * `buffer` - the buffer to read the bytes into.
+    ///
+    /// # Returns
+    /// The amount of bytes read.
+    pub fn read_at(&mut self, pos: u64, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match &self.map {
+            Some(map) => {
+                let start = usize::try_from(pos).map_or(map.len(), |pos| pos.min(map.len()));
+                let count = buffer.len().min(map.len() - start);
+                buffer[..count].copy_from_slice(&map[start..start + count]);
+                Ok(count)
+            }
+            None => {
+                self.file.seek(SeekFrom::Start(pos))?;
+                let mut done = 0;
+                while done < buffer.len() {
+                    match self.file.read(&mut buffer[done..]) {
+                        Ok(0) => break,
+                        Ok(count) => done += count,
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+
+                Ok(done)
+            }
+        }
+    }
+}
+
+/// Memory-maps a file if it is a non-empty regular file.
+fn map_file(file: &File) -> Option<Mmap> {
+    match file.metadata() {
+        // SAFETY: The map is read-only. Reading a page past the end of a mapped file truncated
+        // by another process raises SIGBUS, which is why mapping is opt-in; the file watcher
+        // releases the map as soon as the file changes on disk.
+        Ok(metadata) if metadata.is_file() && metadata.len() > 0 => unsafe { Mmap::map(file) }.ok(),
+        _ => None,
+    }
+}
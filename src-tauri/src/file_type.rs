@@ -0,0 +1,354 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_parsers::{detect_format, BinaryFormat};
+
+/// The type of a file detected from its first bytes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileType {
+    pub mime_type: String,
+    pub description: String,
+}
+
+impl FileType {
+    fn new(mime_type: &str, description: &str) -> Self {
+        Self {
+            mime_type: mime_type.to_string(),
+            description: description.to_string(),
+        }
+    }
+}
+
+/// The magic byte signatures at the start of a file with their MIME types and descriptions.
+/// The formats with a structure parser are recognised by `detect_format` instead.
+const SIGNATURES: &[(&[u8], &str, &str)] = &[
+    (b"\xFF\xD8\xFF", "image/jpeg", "JPEG image"),
+    (b"GIF87a", "image/gif", "GIF image"),
+    (b"GIF89a", "image/gif", "GIF image"),
+    (b"BM", "image/bmp", "BMP image"),
+    (b"II*\0", "image/tiff", "TIFF image"),
+    (b"MM\0*", "image/tiff", "TIFF image"),
+    (b"\0\0\x01\0", "image/vnd.microsoft.icon", "Windows icon"),
+    (b"%PDF-", "application/pdf", "PDF document"),
+    (b"BZh", "application/x-bzip2", "BZIP2 compressed data"),
+    (b"\xFD7zXZ\0", "application/x-xz", "XZ compressed data"),
+    (
+        b"\x28\xB5\x2F\xFD",
+        "application/zstd",
+        "Zstandard compressed data",
+    ),
+    (
+        b"\x04\x22\x4D\x18",
+        "application/x-lz4",
+        "LZ4 compressed data",
+    ),
+    (
+        b"7z\xBC\xAF\x27\x1C",
+        "application/x-7z-compressed",
+        "7-Zip archive",
+    ),
+    (b"Rar!\x1A\x07", "application/vnd.rar", "RAR archive"),
+    (b"\0asm", "application/wasm", "WebAssembly module"),
+    (
+        b"\xFE\xED\xFA\xCE",
+        "application/x-mach-binary",
+        "Mach-O 32-bit executable",
+    ),
+    (
+        b"\xCE\xFA\xED\xFE",
+        "application/x-mach-binary",
+        "Mach-O 32-bit executable",
+    ),
+    (
+        b"\xFE\xED\xFA\xCF",
+        "application/x-mach-binary",
+        "Mach-O 64-bit executable",
+    ),
+    (
+        b"\xCF\xFA\xED\xFE",
+        "application/x-mach-binary",
+        "Mach-O 64-bit executable",
+    ),
+    (
+        b"\xCA\xFE\xBA\xBE",
+        "application/java-vm",
+        "Java class file or Mach-O universal binary",
+    ),
+    (
+        b"SQLite format 3\0",
+        "application/vnd.sqlite3",
+        "SQLite database",
+    ),
+    (b"OggS", "audio/ogg", "Ogg media"),
+    (b"fLaC", "audio/flac", "FLAC audio"),
+    (b"ID3", "audio/mpeg", "MP3 audio"),
+    (
+        b"\x1A\x45\xDF\xA3",
+        "video/x-matroska",
+        "Matroska or WebM video",
+    ),
+    (b"%!PS", "application/postscript", "PostScript document"),
+    (
+        b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1",
+        "application/x-ole-storage",
+        "Microsoft Office document (OLE)",
+    ),
+];
+
+/// Detects the type of a file from its first bytes using magic byte signatures. Files without a
+/// known signature are reported as text if the bytes are valid UTF-8 without control characters.
+///
+/// # Arguments
+/// * `header` - the first bytes of the file; up to 1024 bytes are used.
+///
+/// # Returns
+/// The MIME type and a human-readable description of the file type.
+pub fn detect_file_type(header: &[u8]) -> FileType {
+    if let Some(format) = detect_format(header) {
+        return describe_format(format, header);
+    }
+
+    if let Some(file_type) = detect_container(header) {
+        return file_type;
+    }
+
+    if let Some((_, mime_type, description)) = SIGNATURES
+        .iter()
+        .find(|(signature, _, _)| header.starts_with(signature))
+    {
+        return FileType::new(mime_type, description);
+    }
+
+    if header.is_empty() {
+        return FileType::new("application/x-empty", "Empty file");
+    }
+
+    // The header may end in the middle of a multi-byte character.
+    let text = match std::str::from_utf8(header) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&header[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return FileType::new("application/octet-stream", "Binary data"),
+    };
+
+    match text
+        .chars()
+        .all(|c| !c.is_control() || c.is_ascii_whitespace())
+    {
+        true if text.is_ascii() => FileType::new("text/plain", "ASCII text"),
+        true => FileType::new("text/plain", "UTF-8 text"),
+        false => FileType::new("application/octet-stream", "Binary data"),
+    }
+}
+
+/// Describes a file in a format with a structure parser; executables are described with their
+/// word sizes and kinds.
+fn describe_format(format: BinaryFormat, header: &[u8]) -> FileType {
+    match format {
+        BinaryFormat::Elf => describe_elf(header),
+        BinaryFormat::Pe => describe_pe(header),
+        BinaryFormat::Png => FileType::new("image/png", "PNG image"),
+        BinaryFormat::Zip if header.starts_with(b"PK\x05\x06") => {
+            FileType::new("application/zip", "ZIP archive (empty)")
+        }
+        BinaryFormat::Zip => FileType::new("application/zip", "ZIP archive"),
+        BinaryFormat::Gzip => FileType::new("application/gzip", "GZIP compressed data"),
+    }
+}
+
+/// Gets a field of the header; the offsets come from the file so the range is checked for overflow.
+fn header_field(header: &[u8], offset: usize, size: usize) -> Option<&[u8]> {
+    header.get(offset..offset.checked_add(size)?)
+}
+
+/// Describes an ELF file with its word size and kind.
+fn describe_elf(header: &[u8]) -> FileType {
+    let is_64 = header.get(4) == Some(&2);
+    let big_endian = header.get(5) == Some(&2);
+    let read_uint = |offset: usize, size: usize| -> u64 {
+        header_field(header, offset, size).map_or(0, |bytes| {
+            let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+            match big_endian {
+                true => bytes.iter().fold(0, fold),
+                false => bytes.iter().rev().fold(0, fold),
+            }
+        })
+    };
+
+    // A position-independent executable is a shared object with a program interpreter.
+    let has_interpreter = || {
+        let (ph_offset, ph_size, ph_count) = match is_64 {
+            true => (read_uint(32, 8), read_uint(54, 2), read_uint(56, 2)),
+            false => (read_uint(28, 4), read_uint(42, 2), read_uint(44, 2)),
+        };
+
+        (0..ph_count.min(64)).any(|i| {
+            let offset = ph_offset.saturating_add(i * ph_size);
+            usize::try_from(offset).is_ok_and(|offset| read_uint(offset, 4) == 3)
+        })
+    };
+
+    let (mime_type, kind) = match read_uint(16, 2) {
+        1 => ("application/x-object", "relocatable"),
+        2 => ("application/x-executable", "executable"),
+        3 if has_interpreter() => ("application/x-pie-executable", "pie executable"),
+        3 => ("application/x-sharedlib", "shared object"),
+        4 => ("application/x-coredump", "core file"),
+        _ => ("application/x-elf", "file"),
+    };
+
+    FileType::new(
+        mime_type,
+        &format!("ELF {} {}", if is_64 { "64-bit" } else { "32-bit" }, kind),
+    )
+}
+
+/// Describes a DOS or PE executable with its format and kind.
+fn describe_pe(header: &[u8]) -> FileType {
+    let read_u16 = |offset: usize| {
+        header_field(header, offset, 2).map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let pe_offset = header
+        .get(0x3C..0x40)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+
+    let description = match pe_offset {
+        Some(offset) if header_field(header, offset, 4) == Some(b"PE\0\0") => {
+            let characteristics = offset.checked_add(22).map_or(0, read_u16);
+            let magic = offset.checked_add(24).map_or(0, read_u16);
+
+            let format = match magic {
+                0x20B => "PE32+",
+                _ => "PE32",
+            };
+            let kind = match characteristics & 0x2000 {
+                0 => "executable",
+                _ => "DLL",
+            };
+
+            format!("{} {}", format, kind)
+        }
+        _ => "DOS executable".to_string(),
+    };
+
+    FileType::new(
+        "application/vnd.microsoft.portable-executable",
+        &description,
+    )
+}
+
+/// Detects the formats identified by a signature after the start of the file.
+fn detect_container(header: &[u8]) -> Option<FileType> {
+    let riff = match header.get(8..12) {
+        Some(b"WAVE") => Some(("audio/wav", "WAV audio")),
+        Some(b"AVI ") => Some(("video/x-msvideo", "AVI video")),
+        Some(b"WEBP") => Some(("image/webp", "WebP image")),
+        _ => None,
+    };
+
+    if header.starts_with(b"RIFF") {
+        if let Some((mime_type, description)) = riff {
+            return Some(FileType::new(mime_type, description));
+        }
+    }
+
+    if header.get(4..8) == Some(b"ftyp") {
+        return match header.get(8..12) {
+            Some(b"qt  ") => Some(FileType::new("video/quicktime", "QuickTime video")),
+            Some(b"heic") | Some(b"heix") => Some(FileType::new("image/heic", "HEIC image")),
+            Some(b"avif") => Some(FileType::new("image/avif", "AVIF image")),
+            _ => Some(FileType::new("video/mp4", "MPEG-4 media")),
+        };
+    }
+
+    if header.get(257..262) == Some(b"ustar") {
+        return Some(FileType::new("application/x-tar", "TAR archive"));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description(header: &[u8]) -> String {
+        detect_file_type(header).description
+    }
+
+    #[test]
+    fn magic_bytes_identify_the_type() {
+        assert_eq!(
+            detect_file_type(b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR"),
+            FileType::new("image/png", "PNG image")
+        );
+        assert_eq!(description(b"PK\x05\x06\0\0"), "ZIP archive (empty)");
+        assert_eq!(description(b"\x1F\x8B\x08\0"), "GZIP compressed data");
+        assert_eq!(description(b"\xFF\xD8\xFF\xE0"), "JPEG image");
+        assert_eq!(description(b"RIFF\x24\0\0\0WAVEfmt "), "WAV audio");
+        assert_eq!(description(b"RIFF\x24\0\0\0ABCD"), "Binary data");
+
+        let mut tar = vec![0; 512];
+        tar[..8].copy_from_slice(b"file.txt");
+        tar[257..263].copy_from_slice(b"ustar\0");
+        assert_eq!(description(&tar), "TAR archive");
+
+        assert_eq!(description(b""), "Empty file");
+        assert_eq!(description(b"plain text\r\n"), "ASCII text");
+        // The header ends in the middle of the second character.
+        assert_eq!(description(&"wörld ö".as_bytes()[..8]), "UTF-8 text");
+        assert_eq!(description(b"text\0with nul"), "Binary data");
+    }
+
+    #[test]
+    fn header_offsets_from_the_file_dont_overflow() {
+        let mut pe = vec![0; 0x60];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        pe[0x56..0x58].copy_from_slice(&0x2000u16.to_le_bytes());
+        pe[0x58..0x5A].copy_from_slice(&0x20Bu16.to_le_bytes());
+        assert_eq!(description(&pe), "PE32+ DLL");
+
+        // The optional header is past the end of the header.
+        assert_eq!(description(&pe[..0x44]), "PE32 executable");
+
+        pe[0x3C..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(description(&pe), "DOS executable");
+
+        // A shared object with the program headers at the end of the address space.
+        let mut elf = vec![0; 64];
+        elf[..6].copy_from_slice(b"\x7FELF\x02\x01");
+        elf[16] = 3;
+        elf[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        elf[54..56].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        elf[56..58].copy_from_slice(&64u16.to_le_bytes());
+        assert_eq!(description(&elf), "ELF 64-bit shared object");
+
+        assert_eq!(description(b"\x7FELF\x01"), "ELF 32-bit file");
+    }
+}
@@ -0,0 +1,368 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use base64::prelude::*;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{Metadata, OpenOptions},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    edit_journal::EditJournal,
+    error::AppError,
+    file_type::detect_file_type,
+    format_parsers::detect_format,
+    types::{AppFileState, AppState, DEFAULT_READ_LENGTH},
+};
+
+/// The event emitted when an open file is modified, replaced or removed by another process.
+pub const FILE_CHANGED_EVENT: &str = "file-changed";
+/// The event emitted when bytes are appended to a file in follow mode.
+pub const FILE_APPENDED_EVENT: &str = "file-appended";
+
+/// The size and the modification time of a file on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiskState {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl DiskState {
+    /// Creates the disk state from the metadata of a file.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// Gets the modification time in milliseconds since the Unix epoch.
+    fn modified_millis(&self) -> Option<u64> {
+        self.modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64)
+    }
+}
+
+/// The file changed event data.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FileChanged {
+    pub file_index: usize,
+    /// The size of the file on disk.
+    pub file_size: u64,
+    /// The modification time of the file on disk in milliseconds since the Unix epoch.
+    pub modified: Option<u64>,
+    /// Whether the file was removed or renamed away.
+    pub removed: bool,
+    /// Whether the file has unsaved edits which a reload would discard.
+    pub is_modified: bool,
+}
+
+/// The file appended event data. The data holds at most the maximum read length of bytes
+/// from the end of the file, so `offset` is past the previous end of the file if more was appended.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FileAppended {
+    pub file_index: usize,
+    pub offset: u64,
+    pub file_data: String,
+    pub bytes_read: usize,
+    pub file_size: u64,
+}
+
+/// A change of an open file reported to the frontend.
+enum FileChange {
+    Changed(FileChanged),
+    Appended(FileAppended),
+}
+
+/// The file reload result data.
+#[derive(Serialize, Deserialize)]
+pub struct ReloadResult {
+    /// The size of the file after the reload.
+    pub file_size: u64,
+    /// Whether the file has unsaved edits which prevented the reload.
+    pub conflict: bool,
+}
+
+/// Starts watching an open file for changes made by other processes. The `file-changed` event is
+/// emitted whenever the size or the modification time of the file on disk changes. A previous
+/// watcher of the file is replaced, so this is also called when the file is saved under a new name.
+///
+/// # Arguments
+/// * `app` - the application handle.
+/// * `file_index` - the index of the file to watch.
+///
+/// # Returns
+/// `Ok(())` if the file is being watched; an error otherwise.
+pub fn watch_file(app: &AppHandle, file_index: usize) -> Result<(), AppError> {
+    let app_state = app.state::<AppState>();
+    let path = app_state.with_file(file_index, |file_state| {
+        Ok(PathBuf::from(&file_state.file_name))
+    })?;
+
+    // The directory is watched instead of the file so a file replaced by a rename is still seen.
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    // The directory isn't watched recursively, so the events are matched by the file name.
+    // Backends may report the canonical path of the directory, such as /private/var for /var
+    // or the target of a symbolic link, which differs from the path the file was opened with.
+    let name = path.file_name().map(|name| name.to_os_string());
+    let handler_app = app.clone();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        if let Ok(event) = result {
+            let watched = event.paths.iter().any(|event_path| {
+                event_path
+                    .file_name()
+                    .is_some_and(|event_name| name.as_deref() == Some(event_name))
+            });
+            if !matches!(event.kind, EventKind::Access(_)) && watched {
+                check_file_change(&handler_app, file_index, &path);
+            }
+        }
+    })
+    .map_err(|e| AppError::Io(e.to_string()))?;
+
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Io(e.to_string()))?;
+
+    let previous = app_state.with_file(file_index, |file_state| {
+        Ok(file_state.watcher.replace(watcher))
+    })?;
+
+    // Dropping a watcher may wait for its event thread, which can be waiting for the lock.
+    drop(previous);
+
+    Ok(())
+}
+
+/// Compares the file on disk with its last known state and emits the `file-changed` event if it
+/// differs. In follow mode the `file-appended` event is emitted instead if the file has grown.
+fn check_file_change(app: &AppHandle, file_index: usize, path: &Path) {
+    let disk_state = std::fs::metadata(path)
+        .ok()
+        .map(|metadata| DiskState::from_metadata(&metadata));
+
+    let app_state = app.state::<AppState>();
+    let max_read_length = app_state.max_read_length.load(Ordering::Relaxed);
+    let change = app_state.with_file(file_index, |file_state| {
+        // The file may have been saved under another name since the event was queued.
+        if Path::new(&file_state.file_name) != path || file_state.disk_state == disk_state {
+            return Ok(None);
+        }
+
+        file_state.disk_state = disk_state;
+        file_state.revision += 1;
+
+        // A mapped page past the end of a truncated file can't be read safely, so the file is
+        // read with seek and read until it is reloaded. Reads stop at the real end of the file.
+        file_state.file.unmap();
+        if !file_state.journal.is_modified() {
+            if let Ok(size) = file_state.file.file_size() {
+                file_state.file_size = file_state.file_size.min(size);
+            }
+        }
+
+        if file_state.follow {
+            if let Some(appended) = read_appended(file_state, max_read_length)? {
+                return Ok(Some(FileChange::Appended(appended)));
+            }
+        }
+
+        let removed = disk_state.is_none();
+        let disk_state = disk_state.unwrap_or_default();
+
+        Ok(Some(FileChange::Changed(FileChanged {
+            file_index,
+            file_size: disk_state.size,
+            modified: disk_state.modified_millis(),
+            removed,
+            is_modified: file_state.journal.is_modified(),
+        })))
+    });
+
+    match change {
+        Ok(Some(FileChange::Changed(changed))) => {
+            let _ = app.emit(FILE_CHANGED_EVENT, changed);
+        }
+        Ok(Some(FileChange::Appended(appended))) => {
+            let _ = app.emit(FILE_APPENDED_EVENT, appended);
+        }
+        _ => {}
+    }
+}
+
+/// Extends the view of a followed file with the bytes appended to it. Files with unsaved edits
+/// aren't extended, as the appended bytes can't be placed in the edited view. Extending clears
+/// the redo history of the file as with a reload: the undone edits were made on the view without
+/// the appended bytes, so redoing them would drop those bytes again.
+///
+/// # Arguments
+/// * `file_state` - the state of the followed file.
+/// * `max_read_length` - the maximum amount of appended bytes returned.
+///
+/// # Returns
+/// The last appended bytes; `None` if the file hasn't grown, has unsaved edits or is being saved.
+fn read_appended(
+    file_state: &mut AppFileState,
+    max_read_length: usize,
+) -> Result<Option<FileAppended>, AppError> {
+    if file_state.journal.is_modified() || file_state.saving {
+        return Ok(None);
+    }
+
+    // The size of the open handle; a file replaced by another one is not followed.
+    let previous_size = file_state.file_size;
+    let file_size = file_state.file.file_size()?;
+    if file_size <= previous_size {
+        return Ok(None);
+    }
+
+    file_state.file_size = file_size;
+    file_state.revision += 1;
+    // Only the redo history is lost, as there are no unsaved edits.
+    file_state.journal = EditJournal::new(file_size);
+
+    let offset = previous_size.max(file_size.saturating_sub(max_read_length as u64));
+    let mut buffer = vec![0; (file_size - offset) as usize];
+    let count = file_state
+        .journal
+        .read(&mut file_state.file, offset, &mut buffer)?;
+    buffer.truncate(count);
+
+    Ok(Some(FileAppended {
+        file_index: file_state.file_index,
+        offset,
+        file_data: BASE64_STANDARD.encode(&buffer),
+        bytes_read: count,
+        file_size,
+    }))
+}
+
+/// Switches the follow mode of an open file. In follow mode the bytes appended to the file by
+/// other processes are added to the view and emitted with the `file-appended` event, like `tail -f`;
+/// each append clears the redo history of the file. The file is only followed once it is watched.
+///
+/// # Arguments
+/// * `app` - the application handle.
+/// * `file_index` - the index of the file.
+/// * `follow` - whether the file is followed.
+///
+/// # Returns
+/// The size of the file; bytes appended since the last change event are emitted right away.
+pub fn set_follow_mode(app: &AppHandle, file_index: usize, follow: bool) -> Result<u64, AppError> {
+    let app_state = app.state::<AppState>();
+    let watched = app_state.with_file(file_index, |file_state| Ok(file_state.watcher.is_some()))?;
+    if follow && !watched {
+        watch_file(app, file_index)?;
+    }
+
+    let max_read_length = app_state.max_read_length.load(Ordering::Relaxed);
+    let (file_size, appended) = app_state.with_file(file_index, |file_state| {
+        file_state.follow = follow;
+        let appended = match follow {
+            true => read_appended(file_state, max_read_length)?,
+            false => None,
+        };
+
+        Ok((file_state.file_size, appended))
+    })?;
+
+    if let Some(appended) = appended {
+        let _ = app.emit(FILE_APPENDED_EVENT, appended);
+    }
+
+    Ok(file_size)
+}
+
+/// Reloads an open file from disk. The edit history of the file is cleared and the current
+/// window is re-read from the same position if the position is still within the file.
+///
+/// # Arguments
+/// * `file_state` - the state of the open file to reload.
+/// * `discard_edits` - whether unsaved edits of the file are discarded.
+///
+/// # Returns
+/// The size of the reloaded file; `conflict` is set without reloading if the file has unsaved
+/// edits which weren't discarded. A file being saved can't be reloaded.
+pub fn reload_file_state(
+    file_state: &mut AppFileState,
+    discard_edits: bool,
+) -> Result<ReloadResult, AppError> {
+    if file_state.saving {
+        return Err(AppError::Busy);
+    }
+
+    if file_state.journal.is_modified() && !discard_edits {
+        return Ok(ReloadResult {
+            file_size: file_state.file_size,
+            conflict: true,
+        });
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(file_state.read_write)
+        .open(&file_state.file_name)?;
+    let metadata = file.metadata()?;
+
+    let mut header = vec![0; DEFAULT_READ_LENGTH];
+    let count = file.read(&mut header)?;
+    header.truncate(count);
+
+    file_state.file.replace_file(file);
+    file_state.file_size = metadata.len();
+    file_state.journal = EditJournal::new(metadata.len());
+    file_state.bookmarks.discard_moves();
+    file_state.revision += 1;
+    file_state.disk_state = Some(DiskState::from_metadata(&metadata));
+    file_state.format = detect_format(&header);
+    file_state.file_type = detect_file_type(&header);
+
+    if file_state.prev_seek_pos >= file_state.file_size {
+        file_state.prev_seek_pos = 0;
+    }
+
+    let mut buffer = vec![0; file_state.bytes_at_pos.len()];
+    let count =
+        file_state
+            .journal
+            .read(&mut file_state.file, file_state.prev_seek_pos, &mut buffer)?;
+    buffer.truncate(count);
+    file_state.bytes_at_pos = buffer;
+
+    Ok(ReloadResult {
+        file_size: file_state.file_size,
+        conflict: false,
+    })
+}
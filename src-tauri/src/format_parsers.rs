@@ -0,0 +1,1009 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, read_file_data},
+    templates::TemplateField,
+    types::AppState,
+};
+
+/// The maximum amount of entries such as sections or chunks listed in a structure tree.
+const MAX_FORMAT_ENTRIES: usize = 10_000;
+/// The size of the ZIP end of central directory record without the comment.
+const ZIP_END_RECORD_SIZE: u64 = 22;
+
+/// The binary formats the structure can be parsed of.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryFormat {
+    Elf,
+    /// A DOS MZ executable, usually a PE/COFF image.
+    Pe,
+    Png,
+    Zip,
+    Gzip,
+}
+
+/// Recognises a binary format by the magic bytes at the start of a file.
+///
+/// # Arguments
+/// * `header` - the first bytes of the file.
+///
+/// # Returns
+/// The format or `None` if the format isn't recognised.
+pub fn detect_format(header: &[u8]) -> Option<BinaryFormat> {
+    match header {
+        [0x7F, b'E', b'L', b'F', ..] => Some(BinaryFormat::Elf),
+        [b'M', b'Z', ..] => Some(BinaryFormat::Pe),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(BinaryFormat::Png),
+        [b'P', b'K', 0x03, 0x04, ..] | [b'P', b'K', 0x05, 0x06, ..] => Some(BinaryFormat::Zip),
+        [0x1F, 0x8B, ..] => Some(BinaryFormat::Gzip),
+        _ => None,
+    }
+}
+
+/// Creates a node of a structure tree.
+fn node(
+    name: &str,
+    field_type: &str,
+    offset: u64,
+    size: u64,
+    value: Option<String>,
+    children: Vec<TemplateField>,
+) -> TemplateField {
+    TemplateField {
+        name: name.to_string(),
+        field_type: field_type.to_string(),
+        offset,
+        size,
+        value,
+        children,
+    }
+}
+
+/// Reads the fields of a binary format from an open file.
+struct FormatReader<'a> {
+    app_state: &'a AppState,
+    file_index: usize,
+    file_size: u64,
+    big_endian: bool,
+}
+
+impl FormatReader<'_> {
+    fn bytes(&self, offset: u64, length: u64) -> Result<Vec<u8>, AppError> {
+        if offset.saturating_add(length) > self.file_size {
+            return Err(AppError::InvalidData(format!(
+                "The structure exceeds the end of the file at {}",
+                offset
+            )));
+        }
+
+        let mut buffer = vec![0; length as usize];
+        read_file_data(self.app_state, self.file_index, offset, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reads an unsigned integer of 1, 2, 4 or 8 bytes in the byte order of the format.
+    fn read_uint(&self, offset: u64, size: u64) -> Result<u64, AppError> {
+        let data = self.bytes(offset, size)?;
+        let value = match self.big_endian {
+            true => data.iter().fold(0, |value, b| (value << 8) | *b as u64),
+            false => data
+                .iter()
+                .rev()
+                .fold(0, |value, b| (value << 8) | *b as u64),
+        };
+
+        Ok(value)
+    }
+
+    /// Reads an unsigned integer field into the fields of a node.
+    fn uint(
+        &self,
+        fields: &mut Vec<TemplateField>,
+        name: &str,
+        offset: u64,
+        size: u64,
+    ) -> Result<u64, AppError> {
+        let value = self.read_uint(offset, size)?;
+        let field_type = format!("u{}", size * 8);
+        fields.push(node(
+            name,
+            &field_type,
+            offset,
+            size,
+            Some(value.to_string()),
+            Vec::new(),
+        ));
+        Ok(value)
+    }
+
+    /// Reads a fixed-length ASCII text field into the fields of a node.
+    fn text(
+        &self,
+        fields: &mut Vec<TemplateField>,
+        name: &str,
+        offset: u64,
+        size: u64,
+    ) -> Result<String, AppError> {
+        let text = String::from_utf8_lossy(&self.bytes(offset, size)?)
+            .trim_end_matches('\0')
+            .to_string();
+        fields.push(node(
+            name,
+            "string",
+            offset,
+            size,
+            Some(text.clone()),
+            Vec::new(),
+        ));
+        Ok(text)
+    }
+
+    /// Reads a zero-terminated string starting at a position.
+    fn c_string(&self, offset: u64, max_length: u64) -> Result<String, AppError> {
+        if offset >= self.file_size {
+            return Ok(String::new());
+        }
+
+        let length = max_length.min(self.file_size.saturating_sub(offset));
+        let data = self.bytes(offset, length)?;
+        let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+        Ok(String::from_utf8_lossy(&data[..end]).to_string())
+    }
+}
+
+/// Creates a node reporting a part of a structure which couldn't be read.
+fn error_node(offset: u64, error: &AppError) -> TemplateField {
+    node(
+        "error",
+        "error",
+        offset,
+        0,
+        Some(error.to_string()),
+        Vec::new(),
+    )
+}
+
+/// Parses an entry of a structure such as a section header or a chunk. An entry which can't be
+/// read, for example because the file is truncated, is replaced with an error node so the entries
+/// parsed before it are still shown.
+///
+/// # Arguments
+/// * `entries` - the list the error node is added to.
+/// * `offset` - the position of the entry.
+/// * `parse` - the function parsing the entry.
+///
+/// # Returns
+/// The parsed entry or `None` if it couldn't be read; the list of entries ends there.
+fn parse_entry<T, F>(entries: &mut Vec<TemplateField>, offset: u64, parse: F) -> Option<T>
+where
+    F: FnOnce() -> Result<T, AppError>,
+{
+    match parse() {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            entries.push(error_node(offset, &e));
+            None
+        }
+    }
+}
+
+/// Parses the ELF header, the program headers and the section headers.
+fn parse_elf(reader: &mut FormatReader) -> Result<TemplateField, AppError> {
+    let ident = reader.bytes(0, 16)?;
+    let is_64 = ident[4] == 2;
+    reader.big_endian = ident[5] == 2;
+    let reader = &*reader;
+    let address = if is_64 { 8 } else { 4 };
+
+    let mut header = vec![
+        node(
+            "magic",
+            "bytes",
+            0,
+            4,
+            Some("7F 45 4C 46".to_string()),
+            Vec::new(),
+        ),
+        node(
+            "class",
+            "u8",
+            4,
+            1,
+            Some(if is_64 { "ELF64" } else { "ELF32" }.to_string()),
+            Vec::new(),
+        ),
+        node(
+            "data",
+            "u8",
+            5,
+            1,
+            Some(
+                if reader.big_endian {
+                    "big-endian"
+                } else {
+                    "little-endian"
+                }
+                .to_string(),
+            ),
+            Vec::new(),
+        ),
+    ];
+    reader.uint(&mut header, "version", 6, 1)?;
+    reader.uint(&mut header, "os_abi", 7, 1)?;
+    reader.uint(&mut header, "type", 16, 2)?;
+    reader.uint(&mut header, "machine", 18, 2)?;
+    reader.uint(&mut header, "elf_version", 20, 4)?;
+    let mut pos = 24;
+    reader.uint(&mut header, "entry", pos, address)?;
+    let ph_offset = reader.uint(&mut header, "ph_offset", pos + address, address)?;
+    let sh_offset = reader.uint(&mut header, "sh_offset", pos + 2 * address, address)?;
+    pos += 3 * address;
+    reader.uint(&mut header, "flags", pos, 4)?;
+    let header_size = reader.uint(&mut header, "header_size", pos + 4, 2)?;
+    let ph_entry_size = reader.uint(&mut header, "ph_entry_size", pos + 6, 2)?;
+    let ph_count = reader.uint(&mut header, "ph_count", pos + 8, 2)?;
+    let sh_entry_size = reader.uint(&mut header, "sh_entry_size", pos + 10, 2)?;
+    let sh_count = reader.uint(&mut header, "sh_count", pos + 12, 2)?;
+    let sh_string_index = reader.uint(&mut header, "sh_string_index", pos + 14, 2)?;
+
+    let mut children = vec![node("ELF header", "header", 0, header_size, None, header)];
+
+    let mut program_headers = Vec::new();
+    for i in 0..ph_count.min(MAX_FORMAT_ENTRIES as u64) {
+        let entry = ph_offset.saturating_add(i * ph_entry_size);
+        if entry >= reader.file_size {
+            break;
+        }
+
+        let program_header = parse_entry(&mut program_headers, entry, || {
+            let mut fields = Vec::new();
+            let segment_type = reader.uint(&mut fields, "type", entry, 4)?;
+            let (file_offset, file_size) = match is_64 {
+                true => {
+                    reader.uint(&mut fields, "flags", entry + 4, 4)?;
+                    let file_offset = reader.uint(&mut fields, "offset", entry + 8, 8)?;
+                    reader.uint(&mut fields, "virtual_address", entry + 16, 8)?;
+                    reader.uint(&mut fields, "physical_address", entry + 24, 8)?;
+                    let file_size = reader.uint(&mut fields, "file_size", entry + 32, 8)?;
+                    reader.uint(&mut fields, "memory_size", entry + 40, 8)?;
+                    reader.uint(&mut fields, "align", entry + 48, 8)?;
+                    (file_offset, file_size)
+                }
+                false => {
+                    let file_offset = reader.uint(&mut fields, "offset", entry + 4, 4)?;
+                    reader.uint(&mut fields, "virtual_address", entry + 8, 4)?;
+                    reader.uint(&mut fields, "physical_address", entry + 12, 4)?;
+                    let file_size = reader.uint(&mut fields, "file_size", entry + 16, 4)?;
+                    reader.uint(&mut fields, "memory_size", entry + 20, 4)?;
+                    reader.uint(&mut fields, "flags", entry + 24, 4)?;
+                    reader.uint(&mut fields, "align", entry + 28, 4)?;
+                    (file_offset, file_size)
+                }
+            };
+
+            let value = format!(
+                "type {:#x}, data {:#x}..{:#x}",
+                segment_type,
+                file_offset,
+                file_offset.saturating_add(file_size)
+            );
+            Ok(node(
+                &format!("[{}]", i),
+                "program_header",
+                entry,
+                ph_entry_size,
+                Some(value),
+                fields,
+            ))
+        });
+
+        match program_header {
+            Some(program_header) => program_headers.push(program_header),
+            None => break,
+        }
+    }
+
+    if !program_headers.is_empty() {
+        children.push(node(
+            "Program headers",
+            "program_headers",
+            ph_offset,
+            ph_count * ph_entry_size,
+            None,
+            program_headers,
+        ));
+    }
+
+    // The section names are in the section header string table; without it the names are empty.
+    let names_offset = match sh_string_index < sh_count {
+        true => {
+            let entry = sh_offset.saturating_add(sh_string_index * sh_entry_size);
+            match is_64 {
+                true => reader.read_uint(entry.saturating_add(24), 8).ok(),
+                false => reader.read_uint(entry.saturating_add(16), 4).ok(),
+            }
+        }
+        false => None,
+    };
+
+    let mut section_headers = Vec::new();
+    let mut sections = Vec::new();
+    for i in 0..sh_count.min(MAX_FORMAT_ENTRIES as u64) {
+        let entry = sh_offset.saturating_add(i * sh_entry_size);
+        if entry >= reader.file_size {
+            break;
+        }
+
+        let section_header = parse_entry(&mut section_headers, entry, || {
+            let mut fields = Vec::new();
+            let name_index = reader.uint(&mut fields, "name", entry, 4)?;
+            let section_type = reader.uint(&mut fields, "type", entry + 4, 4)?;
+            let (offset, size) = match is_64 {
+                true => {
+                    reader.uint(&mut fields, "flags", entry + 8, 8)?;
+                    reader.uint(&mut fields, "address", entry + 16, 8)?;
+                    let offset = reader.uint(&mut fields, "offset", entry + 24, 8)?;
+                    let size = reader.uint(&mut fields, "size", entry + 32, 8)?;
+                    reader.uint(&mut fields, "link", entry + 40, 4)?;
+                    reader.uint(&mut fields, "info", entry + 44, 4)?;
+                    reader.uint(&mut fields, "address_align", entry + 48, 8)?;
+                    reader.uint(&mut fields, "entry_size", entry + 56, 8)?;
+                    (offset, size)
+                }
+                false => {
+                    reader.uint(&mut fields, "flags", entry + 8, 4)?;
+                    reader.uint(&mut fields, "address", entry + 12, 4)?;
+                    let offset = reader.uint(&mut fields, "offset", entry + 16, 4)?;
+                    let size = reader.uint(&mut fields, "size", entry + 20, 4)?;
+                    reader.uint(&mut fields, "link", entry + 24, 4)?;
+                    reader.uint(&mut fields, "info", entry + 28, 4)?;
+                    reader.uint(&mut fields, "address_align", entry + 32, 4)?;
+                    reader.uint(&mut fields, "entry_size", entry + 36, 4)?;
+                    (offset, size)
+                }
+            };
+
+            let name = match names_offset {
+                Some(names_offset) => {
+                    reader.c_string(names_offset.saturating_add(name_index), 256)?
+                }
+                None => String::new(),
+            };
+
+            let header = node(
+                &format!("[{}]", i),
+                "section_header",
+                entry,
+                sh_entry_size,
+                Some(name.clone()),
+                fields,
+            );
+
+            // Sections of the type SHT_NOBITS take no space in the file.
+            let in_file = size > 0 && offset.saturating_add(size) <= reader.file_size;
+            let section = match section_type != 0 && section_type != 8 && in_file {
+                true => Some(node(
+                    &name,
+                    "section",
+                    offset,
+                    size,
+                    Some(format!("type {:#x}", section_type)),
+                    Vec::new(),
+                )),
+                false => None,
+            };
+
+            Ok((header, section))
+        });
+
+        match section_header {
+            Some((header, section)) => {
+                section_headers.push(header);
+                sections.extend(section);
+            }
+            None => break,
+        }
+    }
+
+    if !section_headers.is_empty() {
+        children.push(node(
+            "Section headers",
+            "section_headers",
+            sh_offset,
+            sh_count * sh_entry_size,
+            None,
+            section_headers,
+        ));
+    }
+
+    if !sections.is_empty() {
+        sections.sort_by_key(|s| s.offset);
+        let start = sections[0].offset;
+        let end = sections
+            .iter()
+            .map(|s| s.offset + s.size)
+            .max()
+            .unwrap_or(start);
+        children.push(node(
+            "Sections",
+            "sections",
+            start,
+            end - start,
+            None,
+            sections,
+        ));
+    }
+
+    Ok(node("ELF", "elf", 0, reader.file_size, None, children))
+}
+
+/// Parses the DOS header and the PE/COFF headers and section table if present.
+fn parse_pe(reader: &mut FormatReader) -> Result<TemplateField, AppError> {
+    let reader = &*reader;
+    let mut dos_header = Vec::new();
+    reader.text(&mut dos_header, "magic", 0, 2)?;
+    let pe_offset = reader.uint(&mut dos_header, "pe_header_offset", 0x3C, 4)?;
+    let mut children = vec![node("DOS header", "header", 0, 64, None, dos_header)];
+
+    if reader.bytes(pe_offset, 4).ok().as_deref() != Some(b"PE\0\0") {
+        return Ok(node(
+            "DOS executable",
+            "mz",
+            0,
+            reader.file_size,
+            None,
+            children,
+        ));
+    }
+
+    let coff = pe_offset + 4;
+    let coff_header = parse_entry(&mut children, pe_offset, || {
+        let mut coff_header = Vec::new();
+        reader.text(&mut coff_header, "signature", pe_offset, 4)?;
+        reader.uint(&mut coff_header, "machine", coff, 2)?;
+        let section_count = reader.uint(&mut coff_header, "section_count", coff + 2, 2)?;
+        reader.uint(&mut coff_header, "time_date_stamp", coff + 4, 4)?;
+        reader.uint(&mut coff_header, "symbol_table_offset", coff + 8, 4)?;
+        reader.uint(&mut coff_header, "symbol_count", coff + 12, 4)?;
+        let optional_size = reader.uint(&mut coff_header, "optional_header_size", coff + 16, 2)?;
+        reader.uint(&mut coff_header, "characteristics", coff + 18, 2)?;
+        let header = node("COFF header", "header", pe_offset, 24, None, coff_header);
+        Ok((header, section_count, optional_size))
+    });
+
+    let (section_count, optional_size) = match coff_header {
+        Some((header, section_count, optional_size)) => {
+            children.push(header);
+            (section_count, optional_size)
+        }
+        None => return Ok(node("PE", "pe", 0, reader.file_size, None, children)),
+    };
+
+    let optional = coff + 20;
+    if optional_size >= 72 {
+        let optional_header = parse_entry(&mut children, optional, || {
+            let mut optional_header = Vec::new();
+            let magic = reader.uint(&mut optional_header, "magic", optional, 2)?;
+            reader.uint(&mut optional_header, "code_size", optional + 4, 4)?;
+            reader.uint(&mut optional_header, "entry_point", optional + 16, 4)?;
+            match magic {
+                0x20B => reader.uint(&mut optional_header, "image_base", optional + 24, 8)?,
+                _ => reader.uint(&mut optional_header, "image_base", optional + 28, 4)?,
+            };
+            reader.uint(&mut optional_header, "section_alignment", optional + 32, 4)?;
+            reader.uint(&mut optional_header, "file_alignment", optional + 36, 4)?;
+            reader.uint(&mut optional_header, "image_size", optional + 56, 4)?;
+            reader.uint(&mut optional_header, "headers_size", optional + 60, 4)?;
+            reader.uint(&mut optional_header, "subsystem", optional + 68, 2)?;
+            let name = match magic {
+                0x20B => "Optional header (PE32+)",
+                _ => "Optional header (PE32)",
+            };
+            Ok(node(
+                name,
+                "header",
+                optional,
+                optional_size,
+                None,
+                optional_header,
+            ))
+        });
+
+        match optional_header {
+            Some(optional_header) => children.push(optional_header),
+            None => return Ok(node("PE", "pe", 0, reader.file_size, None, children)),
+        }
+    }
+
+    let table = optional + optional_size;
+    let mut section_headers = Vec::new();
+    let mut sections = Vec::new();
+    for i in 0..section_count.min(MAX_FORMAT_ENTRIES as u64) {
+        let entry = table + i * 40;
+        let section_header = parse_entry(&mut section_headers, entry, || {
+            let mut fields = Vec::new();
+            let name = reader.text(&mut fields, "name", entry, 8)?;
+            reader.uint(&mut fields, "virtual_size", entry + 8, 4)?;
+            reader.uint(&mut fields, "virtual_address", entry + 12, 4)?;
+            let raw_size = reader.uint(&mut fields, "raw_data_size", entry + 16, 4)?;
+            let raw_offset = reader.uint(&mut fields, "raw_data_offset", entry + 20, 4)?;
+            reader.uint(&mut fields, "characteristics", entry + 36, 4)?;
+            let header = node(
+                &format!("[{}]", i),
+                "section_header",
+                entry,
+                40,
+                Some(name.clone()),
+                fields,
+            );
+
+            let section = match raw_size > 0 && raw_offset + raw_size <= reader.file_size {
+                true => Some(node(
+                    &name,
+                    "section",
+                    raw_offset,
+                    raw_size,
+                    None,
+                    Vec::new(),
+                )),
+                false => None,
+            };
+
+            Ok((header, section))
+        });
+
+        match section_header {
+            Some((header, section)) => {
+                section_headers.push(header);
+                sections.extend(section);
+            }
+            None => break,
+        }
+    }
+
+    if !section_headers.is_empty() {
+        children.push(node(
+            "Section table",
+            "section_headers",
+            table,
+            section_count * 40,
+            None,
+            section_headers,
+        ));
+    }
+
+    if !sections.is_empty() {
+        sections.sort_by_key(|s| s.offset);
+        let start = sections[0].offset;
+        let end = sections
+            .iter()
+            .map(|s| s.offset + s.size)
+            .max()
+            .unwrap_or(start);
+        children.push(node(
+            "Sections",
+            "sections",
+            start,
+            end - start,
+            None,
+            sections,
+        ));
+    }
+
+    Ok(node("PE", "pe", 0, reader.file_size, None, children))
+}
+
+/// Parses the PNG signature and the chunks up to the `IEND` chunk.
+fn parse_png(reader: &mut FormatReader) -> Result<TemplateField, AppError> {
+    reader.big_endian = true;
+    let reader = &*reader;
+    reader.bytes(0, 8)?;
+    let mut children = vec![node(
+        "signature",
+        "bytes",
+        0,
+        8,
+        Some("89 50 4E 47 0D 0A 1A 0A".to_string()),
+        Vec::new(),
+    )];
+    let mut pos = 8;
+
+    while pos + 12 <= reader.file_size && children.len() <= MAX_FORMAT_ENTRIES {
+        let chunk = parse_entry(&mut children, pos, || {
+            let mut fields = Vec::new();
+            let length = reader.uint(&mut fields, "length", pos, 4)?;
+            let chunk_type = reader.text(&mut fields, "type", pos + 4, 4)?;
+
+            if chunk_type == "IHDR" && length >= 13 {
+                let mut header = Vec::new();
+                reader.uint(&mut header, "width", pos + 8, 4)?;
+                reader.uint(&mut header, "height", pos + 12, 4)?;
+                reader.uint(&mut header, "bit_depth", pos + 16, 1)?;
+                reader.uint(&mut header, "color_type", pos + 17, 1)?;
+                reader.uint(&mut header, "compression", pos + 18, 1)?;
+                reader.uint(&mut header, "filter", pos + 19, 1)?;
+                reader.uint(&mut header, "interlace", pos + 20, 1)?;
+                fields.push(node("data", "ihdr", pos + 8, length, None, header));
+            } else {
+                fields.push(node("data", "bytes", pos + 8, length, None, Vec::new()));
+            }
+
+            reader.uint(&mut fields, "crc", pos + 8 + length, 4)?;
+            let chunk = node(&chunk_type, "chunk", pos, length + 12, None, fields);
+            Ok((chunk, chunk_type == "IEND"))
+        });
+
+        match chunk {
+            Some((chunk, end)) => {
+                pos += chunk.size;
+                children.push(chunk);
+                if end {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    Ok(node("PNG", "png", 0, reader.file_size, None, children))
+}
+
+/// Parses the ZIP local file headers and the central directory.
+fn parse_zip(reader: &mut FormatReader) -> Result<TemplateField, AppError> {
+    let reader = &*reader;
+    let mut children = Vec::new();
+    let mut pos = 0;
+
+    while children.len() < MAX_FORMAT_ENTRIES && reader.read_uint(pos, 4).ok() == Some(0x04034B50) {
+        let local_file = parse_entry(&mut children, pos, || {
+            let mut fields = Vec::new();
+            reader.uint(&mut fields, "signature", pos, 4)?;
+            reader.uint(&mut fields, "version", pos + 4, 2)?;
+            let flags = reader.uint(&mut fields, "flags", pos + 6, 2)?;
+            reader.uint(&mut fields, "compression", pos + 8, 2)?;
+            reader.uint(&mut fields, "modified_time", pos + 10, 2)?;
+            reader.uint(&mut fields, "modified_date", pos + 12, 2)?;
+            reader.uint(&mut fields, "crc32", pos + 14, 4)?;
+            let compressed_size = reader.uint(&mut fields, "compressed_size", pos + 18, 4)?;
+            reader.uint(&mut fields, "uncompressed_size", pos + 22, 4)?;
+            let name_length = reader.uint(&mut fields, "name_length", pos + 26, 2)?;
+            let extra_length = reader.uint(&mut fields, "extra_length", pos + 28, 2)?;
+            let name = reader.text(&mut fields, "name", pos + 30, name_length)?;
+            let data = pos + 30 + name_length + extra_length;
+            fields.push(node(
+                "data",
+                "bytes",
+                data,
+                compressed_size,
+                None,
+                Vec::new(),
+            ));
+            let local_file = node(
+                &name,
+                "local_file",
+                pos,
+                data + compressed_size - pos,
+                None,
+                fields,
+            );
+
+            // The size of the data isn't known before a data descriptor following it.
+            Ok((local_file, flags & 0x08 != 0 && compressed_size == 0))
+        });
+
+        match local_file {
+            Some((local_file, size_unknown)) => {
+                pos += local_file.size;
+                children.push(local_file);
+                if size_unknown {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    // The end of central directory record is followed by a comment of up to 65535 bytes.
+    let search_start = reader
+        .file_size
+        .saturating_sub(ZIP_END_RECORD_SIZE + 0xFFFF);
+    let tail = reader.bytes(search_start, reader.file_size - search_start)?;
+    let end_record = (0..(tail.len() + 1).saturating_sub(ZIP_END_RECORD_SIZE as usize))
+        .rev()
+        .find(|i| tail[*i..*i + 4] == [0x50, 0x4B, 0x05, 0x06])
+        .map(|i| search_start + i as u64);
+
+    if let Some(end) = end_record {
+        let mut fields = Vec::new();
+        reader.uint(&mut fields, "signature", end, 4)?;
+        reader.uint(&mut fields, "disk", end + 4, 2)?;
+        reader.uint(&mut fields, "directory_disk", end + 6, 2)?;
+        reader.uint(&mut fields, "disk_entries", end + 8, 2)?;
+        let entries = reader.uint(&mut fields, "entries", end + 10, 2)?;
+        let directory_size = reader.uint(&mut fields, "directory_size", end + 12, 4)?;
+        let directory_offset = reader.uint(&mut fields, "directory_offset", end + 16, 4)?;
+        let comment_length = reader.uint(&mut fields, "comment_length", end + 20, 2)?;
+
+        let mut directory = Vec::new();
+        let mut pos = directory_offset;
+        for _ in 0..entries.min(MAX_FORMAT_ENTRIES as u64) {
+            let directory_entry = parse_entry(&mut directory, pos, || {
+                if reader.read_uint(pos, 4)? != 0x02014B50 {
+                    return Ok(None);
+                }
+
+                let mut entry = Vec::new();
+                reader.uint(&mut entry, "signature", pos, 4)?;
+                reader.uint(&mut entry, "version_made_by", pos + 4, 2)?;
+                reader.uint(&mut entry, "version_needed", pos + 6, 2)?;
+                reader.uint(&mut entry, "flags", pos + 8, 2)?;
+                reader.uint(&mut entry, "compression", pos + 10, 2)?;
+                reader.uint(&mut entry, "crc32", pos + 16, 4)?;
+                reader.uint(&mut entry, "compressed_size", pos + 20, 4)?;
+                reader.uint(&mut entry, "uncompressed_size", pos + 24, 4)?;
+                let name_length = reader.uint(&mut entry, "name_length", pos + 28, 2)?;
+                let extra_length = reader.uint(&mut entry, "extra_length", pos + 30, 2)?;
+                let comment_length = reader.uint(&mut entry, "comment_length", pos + 32, 2)?;
+                reader.uint(&mut entry, "local_header_offset", pos + 42, 4)?;
+                let name = reader.text(&mut entry, "name", pos + 46, name_length)?;
+                let size = 46 + name_length + extra_length + comment_length;
+                Ok(Some(node(&name, "directory_entry", pos, size, None, entry)))
+            });
+
+            match directory_entry.flatten() {
+                Some(directory_entry) => {
+                    pos += directory_entry.size;
+                    directory.push(directory_entry);
+                }
+                None => break,
+            }
+        }
+
+        children.push(node(
+            "Central directory",
+            "central_directory",
+            directory_offset,
+            directory_size,
+            None,
+            directory,
+        ));
+        children.push(node(
+            "End of central directory",
+            "end_record",
+            end,
+            ZIP_END_RECORD_SIZE + comment_length,
+            None,
+            fields,
+        ));
+    }
+
+    Ok(node("ZIP", "zip", 0, reader.file_size, None, children))
+}
+
+/// Parses the GZIP member header and the trailer of the last member.
+fn parse_gzip(reader: &mut FormatReader) -> Result<TemplateField, AppError> {
+    let reader = &*reader;
+    let mut header = Vec::new();
+    reader.uint(&mut header, "magic", 0, 2)?;
+    reader.uint(&mut header, "compression", 2, 1)?;
+    let flags = reader.uint(&mut header, "flags", 3, 1)?;
+    reader.uint(&mut header, "modified_time", 4, 4)?;
+    reader.uint(&mut header, "extra_flags", 8, 1)?;
+    reader.uint(&mut header, "os", 9, 1)?;
+
+    // A header cut off in the optional fields still shows the fields read before.
+    let mut pos = 10;
+    let optional = (|| {
+        if flags & 0x04 != 0 {
+            let length = reader.uint(&mut header, "extra_length", pos, 2)?;
+            reader.bytes(pos + 2, length)?;
+            header.push(node("extra", "bytes", pos + 2, length, None, Vec::new()));
+            pos += 2 + length;
+        }
+
+        for (flag, name) in [(0x08, "file_name"), (0x10, "comment")] {
+            if flags & flag != 0 {
+                let text = reader.c_string(pos, 0xFFFF)?;
+                let size = text.len() as u64 + 1;
+                header.push(node(name, "string", pos, size, Some(text), Vec::new()));
+                pos += size;
+            }
+        }
+
+        if flags & 0x02 != 0 {
+            reader.uint(&mut header, "header_crc16", pos, 2)?;
+            pos += 2;
+        }
+
+        Ok(())
+    })();
+
+    if let Err(e) = optional {
+        header.push(error_node(pos, &e));
+        let children = vec![node("Header", "header", 0, pos, None, header)];
+        return Ok(node("GZIP", "gzip", 0, reader.file_size, None, children));
+    }
+
+    let mut children = vec![node("Header", "header", 0, pos, None, header)];
+    if reader.file_size >= pos + 8 {
+        let trailer = reader.file_size - 8;
+        children.push(node(
+            "Compressed data",
+            "bytes",
+            pos,
+            trailer - pos,
+            None,
+            Vec::new(),
+        ));
+
+        let mut fields = Vec::new();
+        reader.uint(&mut fields, "crc32", trailer, 4)?;
+        reader.uint(&mut fields, "uncompressed_size", trailer + 4, 4)?;
+        children.push(node("Trailer", "trailer", trailer, 8, None, fields));
+    }
+
+    Ok(node("GZIP", "gzip", 0, reader.file_size, None, children))
+}
+
+/// Parses the structure of an open file in a built-in binary format.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file.
+/// * `format` - the format of the file.
+///
+/// # Returns
+/// The structure tree with the byte ranges of the headers and the data.
+pub fn parse_structure(
+    app_state: &AppState,
+    file_index: usize,
+    format: BinaryFormat,
+) -> Result<TemplateField, AppError> {
+    let mut reader = FormatReader {
+        app_state,
+        file_index,
+        file_size: file_size(app_state, file_index)?,
+        big_endian: false,
+    };
+
+    match format {
+        BinaryFormat::Elf => parse_elf(&mut reader),
+        BinaryFormat::Pe => parse_pe(&mut reader),
+        BinaryFormat::Png => parse_png(&mut reader),
+        BinaryFormat::Zip => parse_zip(&mut reader),
+        BinaryFormat::Gzip => parse_gzip(&mut reader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opens a file with the specified contents in the temporary directory and parses its structure.
+    fn parse(name: &str, data: &[u8]) -> Result<TemplateField, AppError> {
+        let path = std::env::temp_dir().join(format!("hexfp-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+
+        let app_state = AppState::default();
+        let file_index =
+            crate::open_file_state(path.to_string_lossy().to_string(), false, &app_state)?;
+        let format = detect_format(data).unwrap();
+        parse_structure(&app_state, file_index, format)
+    }
+
+    /// Gets the names of the nodes of a tree level.
+    fn names(nodes: &[TemplateField]) -> Vec<&str> {
+        nodes.iter().map(|n| n.name.as_str()).collect()
+    }
+
+    #[test]
+    fn formats_are_detected_by_their_magic_bytes() {
+        assert_eq!(detect_format(b"\x7FELF\x02\x01"), Some(BinaryFormat::Elf));
+        assert_eq!(detect_format(b"MZ\x90\0"), Some(BinaryFormat::Pe));
+        assert_eq!(detect_format(b"\x89PNG\r\n\x1a\n"), Some(BinaryFormat::Png));
+        assert_eq!(detect_format(b"PK\x03\x04"), Some(BinaryFormat::Zip));
+        assert_eq!(detect_format(b"PK\x05\x06"), Some(BinaryFormat::Zip));
+        assert_eq!(detect_format(b"\x1F\x8B"), Some(BinaryFormat::Gzip));
+
+        // A signature cut off by the end of the header isn't a match.
+        assert_eq!(detect_format(b"\x89PNG\r\n"), None);
+        assert_eq!(detect_format(b"PK\x07\x08"), None);
+        assert_eq!(detect_format(b""), None);
+    }
+
+    #[test]
+    fn truncated_png_chunk_ends_the_tree_with_an_error() {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        data.extend_from_slice(&[0; 4]);
+        // The data of the second chunk is cut off.
+        data.extend_from_slice(&100u32.to_be_bytes());
+        data.extend_from_slice(b"IDAT");
+        data.extend_from_slice(&[0; 10]);
+
+        let tree = parse("png", &data).unwrap();
+
+        assert_eq!(names(&tree.children), vec!["signature", "IHDR", "error"]);
+        assert_eq!(tree.children[2].offset, 33);
+        let header = &tree.children[1].children[2];
+        assert_eq!(
+            header
+                .children
+                .iter()
+                .map(|f| f.value.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["4", "2", "8", "6", "0", "0", "0"]
+        );
+    }
+
+    #[test]
+    fn truncated_zip_directory_entry_keeps_the_other_records() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x04034B50u32.to_le_bytes());
+        data.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&5u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(b"a.txthi");
+
+        // The name of the directory entry runs past the end of the file.
+        data.extend_from_slice(&0x02014B50u32.to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        data.extend_from_slice(&200u16.to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(b"a.txt");
+
+        data.extend_from_slice(&0x06054B50u32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        data.extend_from_slice(&51u32.to_le_bytes());
+        data.extend_from_slice(&37u32.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+
+        let tree = parse("zip", &data).unwrap();
+
+        assert_eq!(
+            names(&tree.children),
+            vec!["a.txt", "Central directory", "End of central directory"]
+        );
+        assert_eq!((tree.children[0].offset, tree.children[0].size), (0, 37));
+        assert_eq!(names(&tree.children[1].children), vec!["error"]);
+        assert_eq!(tree.children[1].children[0].offset, 37);
+    }
+
+    #[test]
+    fn unreadable_header_fails_the_parse() {
+        assert!(matches!(
+            parse("elf", b"\x7fELF\x02\x01"),
+            Err(AppError::InvalidData(_))
+        ));
+    }
+}
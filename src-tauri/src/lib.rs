@@ -26,25 +26,72 @@ SOFTWARE.
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use base64::prelude::*;
+use bookmarks::{content_identity, Bookmark, FileBookmarks};
+use byte_analysis::{analyze_bytes, DEFAULT_ENTROPY_BLOCK_SIZE};
 use config::{get_app_config, set_app_config, AppConfig};
+use edit_journal::{EditHistory, EditHistoryEntry, EditJournal};
+use error::AppError;
+use file_diff::{compare_files, seek_difference, DiffMode, DiffRange};
+use file_hash::{hash_range, HashAlgorithm};
+use file_save::save_file_state;
+use file_scan::file_size;
+use file_source::FileSource;
+use file_type::detect_file_type;
+use file_watch::{reload_file_state, set_follow_mode, watch_file, DiskState, ReloadResult};
+use format_parsers::{detect_format, parse_structure};
 use hex_data::{get_data_in_bytes, read_byte_encodings};
+use regex::bytes::Regex;
+use search::{
+    find_matches, search_patterns, search_regex, text_patterns, BytePattern, SearchQuery,
+    DEFAULT_MAX_REGEX_MATCH_LENGTH, DEFAULT_MAX_SEARCH_RESULTS, MAX_REGEX_MATCH_LENGTH,
+};
 use serde::{Deserialize, Serialize};
-use std::io::Seek;
+use std::collections::HashSet;
+use std::io::Read;
 use std::path::Path;
-use std::{
-    io::Read, // Add Write when the file is to be written into.
-};
-use string_encodings::TextDataInPosition;
-use tauri::State;
-use types::{AppFileState, AppFileStateResult, AppState, DataInPosition};
+use std::sync::atomic::{AtomicBool, Ordering};
+use string_encodings::{TextDataInPosition, TextEncoding};
+use string_extract::{scan_strings, DEFAULT_MIN_STRING_LENGTH};
+use tasks::spawn_task;
+use tauri::{ipc::Response, AppHandle, Manager, State};
+use templates::{apply_template, TemplateField, TemplateFile};
+use types::{AppFileState, AppFileStateResult, AppState, DataInPosition, DEFAULT_READ_LENGTH};
+use value_search::{search_value, NumericType, NumericValue};
 
+mod bookmarks;
+mod byte_analysis;
 mod config;
+mod edit_journal;
+mod error;
+mod file_diff;
+mod file_hash;
+mod file_save;
+mod file_scan;
+mod file_source;
+mod file_type;
+mod file_watch;
+mod format_parsers;
 mod hex_data;
+mod piece_table;
+mod search;
 mod string_encodings;
+mod string_extract;
+mod tasks;
+mod templates;
 mod types;
+mod value_search;
 
 #[tokio::main]
 pub async fn run() {
+    let app_state = AppState::default();
+    let config = get_app_config();
+    app_state
+        .max_read_length
+        .store(config.max_read_length(), Ordering::Relaxed);
+    app_state
+        .memory_map_files
+        .store(config.memory_map_files(), Ordering::Relaxed);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_window_state::Builder::new().build())
@@ -52,16 +99,45 @@ pub async fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_window_state::Builder::default().build())
-        .manage(AppState::default())
+        .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             load_settings,
             save_settings,
             open_file,
+            close_file,
+            reload_file,
+            follow_file,
             read_file,
+            read_file_binary,
             get_open_files,
             read_file_current_pos,
             get_data_in_position,
-            get_text_data_in_position
+            get_text_data_in_position,
+            write_bytes,
+            insert_bytes,
+            delete_bytes,
+            save_file,
+            save_file_as,
+            undo_edit,
+            redo_edit,
+            get_edit_history,
+            search_bytes,
+            search_text,
+            search_regex_bytes,
+            search_numeric_value,
+            extract_strings,
+            diff_files,
+            find_difference,
+            calculate_hashes,
+            analyze_file_bytes,
+            get_bookmarks,
+            add_bookmark,
+            remove_bookmark,
+            jump_to_bookmark,
+            apply_struct_template,
+            parse_file_structure,
+            replace_matches,
+            cancel_task
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -85,8 +161,18 @@ async fn load_settings() -> AppConfig {
 /// # Returns
 /// `true` if the settings were saved successfully; `false` otherwise.
 #[tauri::command]
-async fn save_settings(config: AppConfig) -> bool {
-    set_app_config(config)
+async fn save_settings(
+    config: AppConfig,
+    app_state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    app_state
+        .max_read_length
+        .store(config.max_read_length(), Ordering::Relaxed);
+    app_state
+        .memory_map_files
+        .store(config.memory_map_files(), Ordering::Relaxed);
+
+    Ok(set_app_config(config))
 }
 
 /// Opens a file to the application.
@@ -96,43 +182,101 @@ async fn save_settings(config: AppConfig) -> bool {
 /// * `rw` - whether the file should be opened for reading or writing.
 ///
 /// # Returns
-///
+/// The index of the opened file; an error if the file can't be opened or read.
 #[tauri::command]
 async fn open_file(
     file_name: String,
     rw: bool,
+    app: AppHandle,
     app_state: State<'_, AppState>,
-) -> Result<i32, String> {
-    match app_state.file.lock() {
-        Ok(mut state) => {
-            let mut file = std::fs::OpenOptions::new()
-                .write(rw)
-                .read(true)
-                .open(file_name.clone())
-                .unwrap();
+) -> Result<usize, AppError> {
+    let file_index = open_file_state(file_name, rw, &app_state)?;
 
-            let file_len = file.metadata().unwrap().len();
+    // The file stays usable without change detection if it can't be watched, but it isn't
+    // memory-mapped then as a truncation by another process couldn't be noticed.
+    if watch_file(&app, file_index).is_err() {
+        app_state.with_file(file_index, |file_state| {
+            file_state.file.unmap();
+            Ok(())
+        })?;
+    }
 
-            let index = state.len() as i32;
+    Ok(file_index)
+}
 
-            let mut buffer = [0; 1024];
-            match file.read(&mut buffer) {
-                Ok(_) => {}
-                Err(e) => return Err(e.to_string()),
-            }
+/// Opens a file and adds it to the open files of the application.
+///
+/// # Arguments
+/// * `file_name` - the name of the file to open.
+/// * `rw` - whether the file should be opened for reading or writing.
+/// * `app_state` - the application state.
+///
+/// # Returns
+/// The index of the opened file.
+fn open_file_state(file_name: String, rw: bool, app_state: &AppState) -> Result<usize, AppError> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(rw)
+        .read(true)
+        .open(&file_name)?;
+
+    let metadata = file.metadata()?;
+    let file_len = metadata.len();
+
+    let mut buffer = vec![0; DEFAULT_READ_LENGTH];
+    let count = file.read(&mut buffer)?;
+    buffer.truncate(count);
+
+    let format = detect_format(&buffer);
+    let file_type = detect_file_type(&buffer);
+    let bookmarks = FileBookmarks::load(&file_name, content_identity(file_len, &buffer));
 
-            state.push(AppFileState {
-                file: file,
-                file_name: file_name,
-                file_index: index,
-                file_size: file_len,
-                prev_seek_pos: 0,
-                bytes_at_pos: buffer,
-            });
+    let mut files = app_state.file.lock()?;
+    let index = app_state.next_file_index.fetch_add(1, Ordering::Relaxed);
+    files.insert(
+        index,
+        AppFileState {
+            file: FileSource::new(file, app_state.memory_map_files.load(Ordering::Relaxed)),
+            file_index: index,
+            read_write: rw,
+            file_size: file_len,
+            prev_seek_pos: 0,
+            bytes_at_pos: buffer,
+            journal: EditJournal::new(file_len),
+            bookmarks,
+            format,
+            file_type,
+            disk_state: Some(DiskState::from_metadata(&metadata)),
+            watcher: None,
+            follow: false,
+            saving: false,
+            revision: 0,
+            file_name,
+        },
+    );
+
+    Ok(index)
+}
 
-            Ok(index)
+/// Closes an open file. The file handle and the buffers of the file are released and unsaved
+/// edits are discarded; the indices of the other open files stay valid.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to close.
+///
+/// # Returns
+/// `Ok(())` if the file was closed; an error if the file index is invalid.
+#[tauri::command]
+async fn close_file(file_index: usize, app_state: State<'_, AppState>) -> Result<(), AppError> {
+    let file_state = app_state.file.lock()?.remove(&file_index);
+
+    // The file is dropped after the lock is released, as dropping its watcher may wait for
+    // the event thread of the watcher, which can be waiting for the lock.
+    match file_state {
+        Some(file_state) => {
+            drop(file_state);
+            Ok(())
         }
-        Err(e) => Err(e.to_string()),
+        None => Err(AppError::InvalidIndex),
     }
 }
 
@@ -141,9 +285,10 @@ async fn open_file(
 struct FileReadResult {
     file_index: usize,
     file_data: String,
+    bytes_read: usize,
 }
 
-/// Reads the current position in the file from the application.
+/// Reads the current position in the file from the application with the previously read length.
 ///
 /// # Arguments
 /// * `file_index` - the index of the file to read.
@@ -154,13 +299,17 @@ struct FileReadResult {
 async fn read_file_current_pos(
     file_index: usize,
     app_state: State<'_, AppState>,
-) -> Result<FileReadResult, String> {
-    let pos = match app_state.file.lock() {
-        Ok(files) => files[file_index].prev_seek_pos,
-        Err(e) => return Err(e.to_string()),
+) -> Result<FileReadResult, AppError> {
+    let (pos, length) = app_state.with_file(file_index, |file_state| {
+        Ok((file_state.prev_seek_pos, file_state.bytes_at_pos.len()))
+    })?;
+
+    let length = match length {
+        0 => None,
+        length => Some(length),
     };
 
-    read_file(file_index, pos, app_state).await
+    read_file(file_index, pos, length, app_state).await
 }
 
 /// Reads a file from the application.
@@ -168,51 +317,83 @@ async fn read_file_current_pos(
 /// # Arguments
 /// * `file_index` - the index of the file to read.
 /// * `file_pos` - the position in the file to start reading.
+/// * `length` - the amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
 ///
 /// # Returns
-/// The read file content as a base64 encoded string.
+/// The read file content as a base64 encoded string and the amount of bytes read, which is less
+/// than the requested length at the end of the file.
 #[tauri::command]
 async fn read_file(
     file_index: usize,
     file_pos: u64,
+    length: Option<usize>,
     app_state: State<'_, AppState>,
-) -> Result<FileReadResult, String> {
-    let mut buffer: [u8; 1024] = [0; 1024];
+) -> Result<FileReadResult, AppError> {
+    let buffer = read_file_window(file_index, file_pos, length, &app_state)?;
 
-    match app_state.file.lock() {
-        Ok(mut files) => {
-            if file_index >= files.len() {
-                return Err("Invalid file index".to_string());
-            }
-            if file_pos >= files[file_index].file_size {
-                return Err("Invalid file position".to_string());
-            }
+    Ok(FileReadResult {
+        file_index,
+        file_data: BASE64_STANDARD.encode(&buffer),
+        bytes_read: buffer.len(),
+    })
+}
+
+/// Reads a file from the application as raw bytes. The frontend receives the data as an `ArrayBuffer`
+/// without the base64 encoding overhead of `read_file`.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to read.
+/// * `file_pos` - the position in the file to start reading.
+/// * `length` - the amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
+///
+/// # Returns
+/// The read file content; this is shorter than the requested length at the end of the file.
+#[tauri::command]
+async fn read_file_binary(
+    file_index: usize,
+    file_pos: u64,
+    length: Option<usize>,
+    app_state: State<'_, AppState>,
+) -> Result<Response, AppError> {
+    let buffer = read_file_window(file_index, file_pos, length, &app_state)?;
+
+    Ok(Response::new(buffer))
+}
+
+/// Reads a window of bytes from the edited view of a file and stores it as the current window of the file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to read.
+/// * `file_pos` - the position in the file to start reading.
+/// * `length` - the amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
+/// * `app_state` - the application state.
+///
+/// # Returns
+/// The read bytes.
+fn read_file_window(
+    file_index: usize,
+    file_pos: u64,
+    length: Option<usize>,
+    app_state: &AppState,
+) -> Result<Vec<u8>, AppError> {
+    let max_read_length = app_state.max_read_length.load(Ordering::Relaxed);
+    let length = length.unwrap_or(DEFAULT_READ_LENGTH).min(max_read_length);
+    let mut buffer = vec![0; length];
 
-            files[file_index].prev_seek_pos = file_pos;
+    app_state.with_file(file_index, |file_state| {
+        if file_pos >= file_state.file_size {
+            return Err(AppError::OutOfRange);
         }
-        Err(e) => return Err(e.to_string()),
-    }
 
-    match app_state.file.lock() {
-        Ok(mut file) => match file[file_index]
-            .file
-            .seek(std::io::SeekFrom::Start(file_pos))
-        {
-            Ok(_) => match file[file_index].file.read(&mut buffer) {
-                Ok(_) => Ok({
-                    file[file_index].bytes_at_pos = buffer;
-
-                    FileReadResult {
-                        file_index: file_index,
-                        file_data: BASE64_STANDARD.encode(&buffer),
-                    }
-                }),
-                Err(e) => Err(e.to_string()),
-            },
-            Err(e) => Err(e.to_string()),
-        },
-        Err(e) => Err(e.to_string()),
-    }
+        let count = file_state
+            .journal
+            .read(&mut file_state.file, file_pos, &mut buffer)?;
+        buffer.truncate(count);
+        file_state.prev_seek_pos = file_pos;
+        file_state.bytes_at_pos = buffer.clone();
+
+        Ok(buffer)
+    })
 }
 
 /// Returns the list of open files from the Tauri application state.
@@ -223,25 +404,32 @@ async fn read_file(
 /// # Returns
 /// A list of open files.
 #[tauri::command]
-fn get_open_files(app_state: State<'_, AppState>) -> Result<Vec<AppFileStateResult>, String> {
+fn get_open_files(app_state: State<'_, AppState>) -> Result<Vec<AppFileStateResult>, AppError> {
     match app_state.file.lock() {
         Ok(files) => {
             let mut file_list = Vec::new();
 
-            for (_, file) in files.iter().enumerate() {
+            for file in files.values() {
                 let path = Path::new(&file.file_name);
-                let filename = path.file_name().unwrap();
+                let filename = match path.file_name() {
+                    Some(filename) => filename.to_string_lossy().to_string(),
+                    None => file.file_name.clone(),
+                };
 
                 file_list.push(AppFileStateResult {
                     file_name: file.file_name.clone(),
-                    file_index: file.file_index as usize,
+                    file_index: file.file_index,
                     file_size: file.file_size,
-                    file_name_no_path: filename.to_str().unwrap().to_string(),
+                    file_name_no_path: filename,
+                    is_modified: file.journal.is_modified(),
+                    format: file.format,
+                    mime_type: file.file_type.mime_type.clone(),
+                    file_type_description: file.file_type.description.clone(),
                 });
             }
             Ok(file_list)
         }
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -249,41 +437,1028 @@ fn get_open_files(app_state: State<'_, AppState>) -> Result<Vec<AppFileStateResu
 async fn get_text_data_in_position(
     file_index: usize,
     app_state: State<'_, AppState>,
-) -> Result<TextDataInPosition, String> {
-    match app_state.file.lock() {
-        Ok(files) => {
-            let buffer = &files[file_index].bytes_at_pos;
+) -> Result<TextDataInPosition, AppError> {
+    app_state.with_file(file_index, |file_state| {
+        Ok(read_byte_encodings(&file_state.bytes_at_pos))
+    })
+}
 
-            let data = read_byte_encodings(buffer);
-            Ok(data)
+#[tauri::command]
+async fn get_data_in_position(
+    file_index: usize,
+    file_pos: u64,
+    app_state: State<'_, AppState>,
+) -> Result<DataInPosition, AppError> {
+    app_state.with_file(file_index, |file_state| {
+        if file_pos >= file_state.file_size {
+            return Err(AppError::OutOfRange);
         }
-        Err(e) => Err(e.to_string()),
-    }
+
+        // The widest decoded value is 128 bits; bytes past the end of the file read as zero.
+        let mut buffer = [0; 16];
+        file_state
+            .journal
+            .read(&mut file_state.file, file_pos, &mut buffer)?;
+
+        Ok(get_data_in_bytes(&buffer))
+    })
 }
 
+/// Overwrites bytes in the edited view of a file. The file on disk is not modified.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `file_pos` - the position of the first byte to overwrite.
+/// * `data` - the new bytes.
+///
+/// # Returns
+/// The size of the edited file.
 #[tauri::command]
-async fn get_data_in_position(
+async fn write_bytes(
     file_index: usize,
     file_pos: u64,
+    data: Vec<u8>,
     app_state: State<'_, AppState>,
-) -> Result<DataInPosition, String> {
-    match app_state.file.lock() {
-        Ok(files) => {
-            let buffer_pos = match file_pos.checked_sub(files[file_index].prev_seek_pos) {
-                Some(pos) => pos,
-                None => 0,
-            };
+) -> Result<u64, AppError> {
+    edit_file(file_index, &app_state, |journal| {
+        journal.overwrite(file_pos, data)
+    })
+}
 
-            if (buffer_pos as usize) >= files[file_index].bytes_at_pos.len() {
-                return Err("Invalid file position".to_string());
-            }
+/// Inserts bytes into the edited view of a file. The file on disk is not modified.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `file_pos` - the position to insert the bytes at.
+/// * `data` - the bytes to insert.
+///
+/// # Returns
+/// The size of the edited file.
+#[tauri::command]
+async fn insert_bytes(
+    file_index: usize,
+    file_pos: u64,
+    data: Vec<u8>,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    edit_file(file_index, &app_state, |journal| {
+        journal.insert(file_pos, data)
+    })
+}
 
-            let buffer = &files[file_index].bytes_at_pos[buffer_pos as usize..1024];
+/// Deletes bytes from the edited view of a file. The file on disk is not modified.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `file_pos` - the position of the first byte to delete.
+/// * `length` - the amount of bytes to delete.
+///
+/// # Returns
+/// The size of the edited file.
+#[tauri::command]
+async fn delete_bytes(
+    file_index: usize,
+    file_pos: u64,
+    length: u64,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    edit_file(file_index, &app_state, |journal| {
+        journal.delete(file_pos, length)
+    })
+}
+
+/// Applies an edit to the journal of an open file and updates the file size accordingly.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `app_state` - the application state.
+/// * `edit` - the edit to apply to the journal of the file.
+///
+/// # Returns
+/// The result of the edit; an error if the file is being saved.
+fn edit_file<T, F>(file_index: usize, app_state: &AppState, edit: F) -> Result<T, AppError>
+where
+    F: FnOnce(&mut EditJournal) -> Result<T, AppError>,
+{
+    edit_file_revision(file_index, app_state, None, edit)
+}
 
-            let data = get_data_in_bytes(buffer);
+/// Edits the journal of a file like `edit_file` if the file hasn't changed since a revision,
+/// so an edit computed from an earlier read of the file isn't applied to a changed view.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `app_state` - the application state.
+/// * `revision` - the revision the edit was computed from; any revision if not specified.
+/// * `edit` - the function to edit the journal with.
+///
+/// # Returns
+/// The result of the edit; `AppError::Conflict` if the file has changed since the revision.
+fn edit_file_revision<T, F>(
+    file_index: usize,
+    app_state: &AppState,
+    revision: Option<u64>,
+    edit: F,
+) -> Result<T, AppError>
+where
+    F: FnOnce(&mut EditJournal) -> Result<T, AppError>,
+{
+    app_state.with_file(file_index, |file_state| {
+        if file_state.saving {
+            return Err(AppError::Busy);
+        }
 
-            Ok(data)
+        if revision.is_some_and(|revision| revision != file_state.revision) {
+            return Err(AppError::Conflict);
         }
-        Err(e) => Err(e.to_string()),
+
+        let result = edit(&mut file_state.journal);
+        let shifts = file_state.journal.take_shifts();
+        file_state.bookmarks.shift(&shifts);
+        let result = result?;
+        file_state.file_size = file_state.journal.size();
+        file_state.revision += 1;
+
+        Ok(result)
+    })
+}
+
+/// The undo or redo result data.
+#[derive(Serialize, Deserialize)]
+struct UndoRedoResult {
+    file_size: u64,
+    step: Option<EditHistoryEntry>,
+}
+
+/// Undoes the most recent edit step of a file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to undo the edit of.
+///
+/// # Returns
+/// The size of the edited file and the undone step; the step is `None` if there was nothing to undo.
+#[tauri::command]
+async fn undo_edit(
+    file_index: usize,
+    app_state: State<'_, AppState>,
+) -> Result<UndoRedoResult, AppError> {
+    edit_file(file_index, &app_state, |journal| {
+        let step = journal.undo();
+        Ok(UndoRedoResult {
+            file_size: journal.size(),
+            step,
+        })
+    })
+}
+
+/// Redoes the most recently undone edit step of a file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to redo the edit of.
+///
+/// # Returns
+/// The size of the edited file and the redone step; the step is `None` if there was nothing to redo.
+#[tauri::command]
+async fn redo_edit(
+    file_index: usize,
+    app_state: State<'_, AppState>,
+) -> Result<UndoRedoResult, AppError> {
+    edit_file(file_index, &app_state, |journal| {
+        let step = journal.redo();
+        Ok(UndoRedoResult {
+            file_size: journal.size(),
+            step,
+        })
+    })
+}
+
+/// Gets the undo and redo history of a file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+///
+/// # Returns
+/// The undo and redo history of the file.
+#[tauri::command]
+async fn get_edit_history(
+    file_index: usize,
+    app_state: State<'_, AppState>,
+) -> Result<EditHistory, AppError> {
+    app_state.with_file(file_index, |file_state| Ok(file_state.journal.history()))
+}
+
+/// Saves the edited view of a file over the file on disk. The file can't be edited while it
+/// is being saved.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to save.
+///
+/// # Returns
+/// `Ok(())` if the file was saved successfully; an error if the file was opened as read-only or the save failed.
+#[tauri::command]
+async fn save_file(file_index: usize, app: AppHandle) -> Result<(), AppError> {
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        let app_state = app.state::<AppState>();
+        let file_name =
+            app_state.with_file(file_index, |file_state| match file_state.read_write {
+                true => Ok(file_state.file_name.clone()),
+                false => Err(AppError::ReadOnly),
+            })?;
+
+        save_file_state(&app_state, file_index, &file_name, true)
+    });
+
+    task.await?
+}
+
+/// Saves the edited view of a file into a new file. The open file is switched to the new file
+/// which is opened for reading and writing. The file can't be edited while it is being saved.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to save.
+/// * `file_name` - the name of the file to save into.
+///
+/// # Returns
+/// `Ok(())` if the file was saved successfully; an error otherwise.
+#[tauri::command]
+async fn save_file_as(
+    file_index: usize,
+    file_name: String,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        let app_state = app.state::<AppState>();
+        let result = save_file_state(&app_state, file_index, &file_name, true);
+
+        // The watcher follows the saved copy.
+        let _ = watch_file(&app, file_index);
+
+        result
+    });
+
+    task.await?
+}
+
+/// Reloads a file from disk after it was changed by another process. The edit history of the
+/// file is cleared.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to reload.
+/// * `discard_edits` - whether unsaved edits of the file are discarded. Defaults to `false`.
+///
+/// # Returns
+/// The size of the reloaded file; `conflict` is set and the file is not reloaded if the file has
+/// unsaved edits and `discard_edits` is not set.
+#[tauri::command]
+async fn reload_file(
+    file_index: usize,
+    discard_edits: Option<bool>,
+    app_state: State<'_, AppState>,
+) -> Result<ReloadResult, AppError> {
+    app_state.with_file(file_index, |file_state| {
+        reload_file_state(file_state, discard_edits.unwrap_or(false))
+    })
+}
+
+/// Switches the follow mode of a file. In follow mode the bytes appended to the file by other
+/// processes are added to the view and emitted with the `file-appended` event; each append
+/// clears the redo history of the file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `follow` - whether the file is followed.
+///
+/// # Returns
+/// The size of the file.
+#[tauri::command]
+async fn follow_file(file_index: usize, follow: bool, app: AppHandle) -> Result<u64, AppError> {
+    set_follow_mode(&app, file_index, follow)
+}
+
+/// Runs a function with the bookmarks of an open file.
+fn with_bookmarks<T, F>(file_index: usize, app_state: &AppState, f: F) -> Result<T, AppError>
+where
+    F: FnOnce(&mut FileBookmarks) -> Result<T, AppError>,
+{
+    app_state.with_file(file_index, |file_state| f(&mut file_state.bookmarks))
+}
+
+/// Gets the bookmarks and the named regions of a file in the order of their positions.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+///
+/// # Returns
+/// The bookmarks of the file.
+#[tauri::command]
+async fn get_bookmarks(
+    file_index: usize,
+    app_state: State<'_, AppState>,
+) -> Result<Vec<Bookmark>, AppError> {
+    with_bookmarks(file_index, &app_state, |bookmarks| {
+        Ok(bookmarks.bookmarks.clone())
+    })
+}
+
+/// Adds a bookmark or a named region to a file. The bookmarks are stored in the application
+/// configuration directory keyed by the file path. The bookmarks move along with the bytes they
+/// refer to when the file is edited; bookmarks moved by unsaved edits are stored when the file is saved.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `offset` - the position of the bookmark.
+/// * `length` - the length of the region; zero for a bookmark of a single position.
+/// * `label` - the name of the bookmark.
+/// * `comment` - a free-form comment.
+/// * `color` - the color of the region as a CSS color value.
+///
+/// # Returns
+/// The added bookmark.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn add_bookmark(
+    file_index: usize,
+    offset: u64,
+    length: u64,
+    label: String,
+    comment: Option<String>,
+    color: Option<String>,
+    app_state: State<'_, AppState>,
+) -> Result<Bookmark, AppError> {
+    // A bookmark of a single position must point at a byte of the file.
+    let size = file_size(&app_state, file_index)?;
+    if offset.saturating_add(length.max(1)) > size {
+        return Err(AppError::OutOfRange);
+    }
+
+    with_bookmarks(file_index, &app_state, |bookmarks| {
+        bookmarks.add(offset, length, label, comment.unwrap_or_default(), color)
+    })
+}
+
+/// Removes a bookmark from a file.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `id` - the identifier of the bookmark.
+///
+/// # Returns
+/// `true` if the bookmark existed; `false` otherwise.
+#[tauri::command]
+async fn remove_bookmark(
+    file_index: usize,
+    id: u64,
+    app_state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    with_bookmarks(file_index, &app_state, |bookmarks| bookmarks.remove(id))
+}
+
+/// Moves the current position of a file to a bookmark and reads the file from there.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `id` - the identifier of the bookmark.
+/// * `length` - the amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
+///
+/// # Returns
+/// The read file content as a base64 encoded string and the amount of bytes read.
+#[tauri::command]
+async fn jump_to_bookmark(
+    file_index: usize,
+    id: u64,
+    length: Option<usize>,
+    app_state: State<'_, AppState>,
+) -> Result<FileReadResult, AppError> {
+    let offset = with_bookmarks(file_index, &app_state, |bookmarks| {
+        match bookmarks.get(id) {
+            Some(bookmark) => Ok(bookmark.offset),
+            None => Err(AppError::InvalidArgument("Invalid bookmark".to_string())),
+        }
+    })?;
+
+    read_file(file_index, offset, length, app_state).await
+}
+
+/// Applies a struct template to a file. The templates are defined in a TOML file or in a JSON
+/// file with the `.json` extension; see `TemplateFile` for the format.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `template_file` - the name of the template file.
+/// * `offset` - the position to apply the root struct at.
+///
+/// # Returns
+/// The tree of the decoded fields with their positions and sizes.
+#[tauri::command]
+async fn apply_struct_template(
+    file_index: usize,
+    template_file: String,
+    offset: u64,
+    app: AppHandle,
+) -> Result<TemplateField, AppError> {
+    let templates = TemplateFile::load(&template_file)?;
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        apply_template(&app.state::<AppState>(), file_index, &templates, offset)
+    });
+
+    task.await?
+}
+
+/// Parses the structure of a file in a built-in binary format recognised when the file was
+/// opened: ELF, PE, PNG, ZIP or GZIP.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+///
+/// # Returns
+/// The structure tree with the byte ranges of the headers and the data, or `null` if the format
+/// of the file isn't recognised. An entry which can't be read ends its list with an `error` node;
+/// only an unreadable top-level header fails the command.
+#[tauri::command]
+async fn parse_file_structure(
+    file_index: usize,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<Option<TemplateField>, AppError> {
+    let format = app_state.with_file(file_index, |file_state| Ok(file_state.format))?;
+
+    let format = match format {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        parse_structure(&app.state::<AppState>(), file_index, format)
+    });
+
+    task.await?.map(Some)
+}
+
+/// Starts searching the whole file for a hexadecimal byte pattern such as `4D 5A ?? 00 ?F`,
+/// where `?` matches any nibble. The matches are emitted in batches with the `search-matches`
+/// event and the progress with the `task-progress` event until the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to search.
+/// * `pattern` - the hexadecimal pattern to search for.
+/// * `max_results` - the maximum amount of matches to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the search task which can be used to cancel the search.
+#[tauri::command]
+async fn search_bytes(
+    file_index: usize,
+    pattern: String,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let pattern = BytePattern::parse(&pattern)?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index)?;
+
+    Ok(spawn_task(app, move |context| {
+        search_patterns(context, file_index, &[(pattern, None)], max_results)
+    }))
+}
+
+/// Starts searching the whole file for text encoded in one or more text encodings. ASCII letters
+/// can be matched case-insensitively in every encoding. The matches are emitted in batches with
+/// the `search-matches` event and the progress with the `task-progress` event until the
+/// `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to search.
+/// * `text` - the text to search for.
+/// * `encodings` - the encodings to search the text in.
+/// * `case_sensitive` - whether the letter case must match.
+/// * `max_results` - the maximum amount of matches to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the search task which can be used to cancel the search.
+#[tauri::command]
+async fn search_text(
+    file_index: usize,
+    text: String,
+    encodings: Vec<TextEncoding>,
+    case_sensitive: bool,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let patterns = text_patterns(&text, &encodings, case_sensitive)?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index)?;
+
+    Ok(spawn_task(app, move |context| {
+        search_patterns(context, file_index, &patterns, max_results)
+    }))
+}
+
+/// Starts searching the whole file with a byte-oriented regular expression. Unicode mode can be
+/// turned off with `(?-u)` to match arbitrary bytes, e.g. `(?-u)\x4D\x5A.{2}`. The matches are
+/// emitted in batches with the `search-matches` event and the progress with the `task-progress`
+/// event until the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to search.
+/// * `pattern` - the regular expression to search with.
+/// * `max_match_length` - the maximum length of a match spanning two read chunks. Defaults to 4096 bytes
+///   and is limited to 1 MiB.
+/// * `max_results` - the maximum amount of matches to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the search task which can be used to cancel the search.
+#[tauri::command]
+async fn search_regex_bytes(
+    file_index: usize,
+    pattern: String,
+    max_match_length: Option<usize>,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let regex = Regex::new(&pattern).map_err(|e| AppError::InvalidArgument(e.to_string()))?;
+    let max_match_length = max_match_length
+        .unwrap_or(DEFAULT_MAX_REGEX_MATCH_LENGTH)
+        .min(MAX_REGEX_MATCH_LENGTH);
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index)?;
+
+    Ok(spawn_task(app, move |context| {
+        search_regex(context, file_index, &regex, max_match_length, max_results)
+    }))
+}
+
+/// Starts searching the whole file for a numeric value such as a length field. The matches are
+/// emitted in batches with the `search-matches` event and the progress with the `task-progress`
+/// event until the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to search.
+/// * `value` - the decimal value to search for.
+/// * `value_type` - the numeric type of the value.
+/// * `big_endian` - whether the value is stored in the big-endian byte order.
+/// * `tolerance` - the maximum difference of a matching floating point value. Defaults to 0.
+/// * `aligned` - whether to only check the offsets which are a multiple of the value size.
+/// * `max_results` - the maximum amount of matches to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the search task which can be used to cancel the search.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn search_numeric_value(
+    file_index: usize,
+    value: String,
+    value_type: NumericType,
+    big_endian: bool,
+    tolerance: Option<f64>,
+    aligned: bool,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let value = NumericValue::parse(&value, value_type, big_endian, tolerance.unwrap_or(0.0))?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index)?;
+
+    Ok(spawn_task(app, move |context| {
+        search_value(context, file_index, &value, aligned, max_results)
+    }))
+}
+
+/// Starts extracting the runs of printable characters from the whole file like `strings(1)`.
+/// The strings are emitted in batches with the `strings-found` event and the progress with the
+/// `task-progress` event until the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to scan.
+/// * `encodings` - the encodings to look for strings in.
+/// * `min_length` - the minimum amount of characters in a string. Defaults to 4.
+/// * `max_results` - the maximum amount of strings to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the extraction task which can be used to cancel the extraction.
+#[tauri::command]
+async fn extract_strings(
+    file_index: usize,
+    encodings: Vec<TextEncoding>,
+    min_length: Option<usize>,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    if encodings.is_empty() {
+        return Err(AppError::InvalidArgument(
+            "No string encodings specified".to_string(),
+        ));
+    }
+
+    let min_length = min_length.unwrap_or(DEFAULT_MIN_STRING_LENGTH).max(1);
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index)?;
+
+    Ok(spawn_task(app, move |context| {
+        scan_strings(context, file_index, &encodings, min_length, max_results)
+    }))
+}
+
+/// Starts comparing two open files. The differing ranges are emitted in ascending order with the
+/// `diff-results` event and the progress with the `task-progress` event before the
+/// `task-finished` event.
+///
+/// # Arguments
+/// * `file_index_a` - the index of the first file.
+/// * `file_index_b` - the index of the second file.
+/// * `mode` - `bytes` to compare the bytes at the same positions or `insertions` to detect
+///   inserted and deleted bytes in files of different lengths.
+/// * `max_results` - the maximum amount of differing ranges to report. Defaults to 100 000.
+///
+/// # Returns
+/// The identifier of the comparison task which can be used to cancel the comparison.
+#[tauri::command]
+async fn diff_files(
+    file_index_a: usize,
+    file_index_b: usize,
+    mode: DiffMode,
+    max_results: Option<usize>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS);
+    file_size(&app_state, file_index_a)?;
+    file_size(&app_state, file_index_b)?;
+
+    Ok(spawn_task(app, move |context| {
+        compare_files(context, file_index_a, file_index_b, mode, max_results)
+    }))
+}
+
+/// Finds the next or the previous difference between two open files from a position.
+///
+/// # Arguments
+/// * `file_index_a` - the index of the first file.
+/// * `file_index_b` - the index of the second file.
+/// * `mode` - how the files are compared; in the `insertions` mode the offset is a position of the first file.
+/// * `offset` - the position to start from; the difference the position is in is skipped.
+/// * `backward` - whether to find the previous difference instead of the next one.
+///
+/// # Returns
+/// The differing range or `null` if there are no more differences in the direction.
+#[tauri::command]
+async fn find_difference(
+    file_index_a: usize,
+    file_index_b: usize,
+    mode: DiffMode,
+    offset: u64,
+    backward: bool,
+    app: AppHandle,
+) -> Result<Option<DiffRange>, AppError> {
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        seek_difference(
+            &app.state::<AppState>(),
+            file_index_a,
+            file_index_b,
+            mode,
+            offset,
+            backward,
+        )
+    });
+
+    task.await?
+}
+
+/// Starts calculating hashes and checksums over a range of the file in a single pass. The
+/// progress is emitted with the `task-progress` event and the results with the `hash-results`
+/// event before the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `algorithms` - the algorithms to calculate.
+/// * `start` - the position of the range. Defaults to the start of the file.
+/// * `length` - the length of the range. Defaults to the rest of the file.
+///
+/// # Returns
+/// The identifier of the hash calculation task which can be used to cancel the calculation.
+#[tauri::command]
+async fn calculate_hashes(
+    file_index: usize,
+    algorithms: Vec<HashAlgorithm>,
+    start: Option<u64>,
+    length: Option<u64>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let start = start.unwrap_or(0);
+    if algorithms.is_empty() {
+        return Err(AppError::InvalidArgument(
+            "No hash algorithms specified".to_string(),
+        ));
+    }
+
+    if start > file_size(&app_state, file_index)? {
+        return Err(AppError::OutOfRange);
+    }
+
+    Ok(spawn_task(app, move |context| {
+        hash_range(context, file_index, &algorithms, start, length)
+    }))
+}
+
+/// Starts calculating the byte histogram of a range of the file and the entropy series of the
+/// whole file. The progress is emitted with the `task-progress` event and the results with the
+/// `byte-analysis` event before the `task-finished` event.
+///
+/// # Arguments
+/// * `file_index` - the index of the file.
+/// * `start` - the position of the histogram range. Defaults to the start of the file.
+/// * `length` - the length of the histogram range. Defaults to the rest of the file.
+/// * `block_size` - the size of an entropy block. Defaults to 4096 bytes; increased for files
+///   which would have more than a million blocks.
+///
+/// # Returns
+/// The identifier of the analysis task which can be used to cancel the analysis.
+#[tauri::command]
+async fn analyze_file_bytes(
+    file_index: usize,
+    start: Option<u64>,
+    length: Option<u64>,
+    block_size: Option<u64>,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let start = start.unwrap_or(0);
+    let block_size = block_size.unwrap_or(DEFAULT_ENTROPY_BLOCK_SIZE);
+    if start > file_size(&app_state, file_index)? {
+        return Err(AppError::OutOfRange);
+    }
+
+    Ok(spawn_task(app, move |context| {
+        analyze_bytes(context, file_index, start, length, block_size)
+    }))
+}
+
+/// Requests a running background task such as a search to stop.
+///
+/// # Arguments
+/// * `task_id` - the identifier of the task to cancel.
+///
+/// # Returns
+/// `true` if the task was running; `false` otherwise.
+#[tauri::command]
+async fn cancel_task(task_id: u64, app_state: State<'_, AppState>) -> Result<bool, AppError> {
+    Ok(app_state.tasks.cancel(task_id))
+}
+
+/// The search and replace result data.
+#[derive(Serialize, Deserialize)]
+struct ReplaceResult {
+    /// The positions of the replaced matches before the replacement.
+    offsets: Vec<u64>,
+    file_size: u64,
+    dry_run: bool,
+}
+
+/// Replaces the matches of a byte or text search with a replacement byte sequence of any length.
+/// The replacement is recorded as a single undo step. The file is searched without holding the
+/// state lock, so the replacement fails if the file changed during the search.
+///
+/// # Arguments
+/// * `file_index` - the index of the file to edit.
+/// * `query` - the byte or text search query.
+/// * `replacement` - the bytes to replace the matches with; empty removes the matches.
+/// * `offsets` - the positions of the matches to replace; all matches are replaced if not specified.
+/// * `dry_run` - whether to only return the positions of the matches which would be replaced.
+/// * `max_results` - the maximum amount of matches to replace when `offsets` isn't specified.
+///   Defaults to 100 000.
+///
+/// # Returns
+/// The positions of the replaced matches and the size of the edited file; an error if a position
+/// in `offsets` isn't a match or `AppError::Conflict` if the file changed during the search.
+#[tauri::command]
+async fn replace_matches(
+    file_index: usize,
+    query: SearchQuery,
+    replacement: Vec<u8>,
+    offsets: Option<Vec<u64>>,
+    dry_run: bool,
+    max_results: Option<usize>,
+    app: AppHandle,
+) -> Result<ReplaceResult, AppError> {
+    let patterns = query.patterns()?;
+    let offsets: Option<HashSet<u64>> = offsets.map(|offsets| offsets.into_iter().collect());
+    let max_results = match &offsets {
+        Some(offsets) => offsets.len(),
+        None => max_results.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS),
+    };
+
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        let app_state = app.state::<AppState>();
+        let revision = app_state.with_file(file_index, |file_state| Ok(file_state.revision))?;
+        let matches = find_matches(
+            &app_state,
+            file_index,
+            &patterns,
+            offsets.as_ref(),
+            max_results,
+            &AtomicBool::new(false),
+        )?;
+
+        if let Some(offsets) = &offsets {
+            let found: HashSet<u64> = matches.iter().map(|m| m.offset).collect();
+            if let Some(offset) = offsets.difference(&found).min() {
+                return Err(AppError::InvalidArgument(format!(
+                    "No match at position {}",
+                    offset
+                )));
+            }
+        }
+
+        let ranges: Vec<(u64, u64)> = matches.iter().map(|m| (m.offset, m.length)).collect();
+        let file_size = match dry_run {
+            true => file_size(&app_state, file_index)?,
+            false => edit_file_revision(file_index, &app_state, Some(revision), |journal| {
+                journal.replace_ranges(&ranges, &replacement)
+            })?,
+        };
+
+        Ok(ReplaceResult {
+            offsets: ranges.iter().map(|r| r.0).collect(),
+            file_size,
+            dry_run,
+        })
+    });
+
+    task.await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a file with the specified contents in the temporary directory.
+    fn temp_file(name: &str, data: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("hexff-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn unknown_file_index_is_rejected() {
+        let app_state = AppState::default();
+
+        assert_eq!(
+            read_file_window(0, 0, None, &app_state).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(
+            edit_file(0, &app_state, |journal| Ok(journal.size())).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(
+            with_bookmarks(0, &app_state, |bookmarks| Ok(bookmarks.bookmarks.len())).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(file_size(&app_state, 0).err(), Some(AppError::InvalidIndex));
+    }
+
+    #[test]
+    fn closed_file_index_is_stale() {
+        let app_state = AppState::default();
+        let first = open_file_state(temp_file("stale-a", b"first"), false, &app_state).unwrap();
+        let second = open_file_state(temp_file("stale-b", b"second"), false, &app_state).unwrap();
+
+        app_state.file.lock().unwrap().remove(&first);
+
+        assert_eq!(
+            read_file_window(first, 0, None, &app_state).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(
+            edit_file(first, &app_state, |journal| journal.insert(0, vec![0])).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(
+            file_scan::read_file_data(&app_state, first, 0, &mut [0; 4]).err(),
+            Some(AppError::InvalidIndex)
+        );
+        assert_eq!(
+            file_scan::scan_file(&app_state, first, 0..5, 0, &AtomicBool::new(false), |_| {
+                true
+            })
+            .err(),
+            Some(AppError::InvalidIndex)
+        );
+
+        // The other file keeps its index and a new file never reuses the closed index.
+        assert_eq!(
+            read_file_window(second, 0, None, &app_state).ok(),
+            Some(b"second".to_vec())
+        );
+        let third = open_file_state(temp_file("stale-c", b"third"), false, &app_state).unwrap();
+        assert!(third != first && third != second);
+    }
+
+    #[test]
+    fn position_past_end_of_file_is_rejected() {
+        let app_state = AppState::default();
+        let file_index = open_file_state(temp_file("range", b"data"), false, &app_state).unwrap();
+
+        assert_eq!(
+            read_file_window(file_index, 4, None, &app_state).err(),
+            Some(AppError::OutOfRange)
+        );
+        assert_eq!(
+            edit_file(file_index, &app_state, |journal| journal.delete(2, 3)).err(),
+            Some(AppError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn pattern_matches_follow_the_overlap_rule() {
+        let app_state = AppState::default();
+        let file_index =
+            open_file_state(temp_file("overlap", b"abcabc"), false, &app_state).unwrap();
+        let cancel = AtomicBool::new(false);
+        let patterns = vec![
+            (BytePattern::parse("62 63").unwrap(), None),
+            (BytePattern::parse("?? ?? ??").unwrap(), None),
+            (BytePattern::parse("61").unwrap(), None),
+        ];
+
+        let matches = find_matches(&app_state, file_index, &patterns, None, 10, &cancel).unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.offset, m.length))
+                .collect::<Vec<_>>(),
+            vec![(0, 3), (3, 3)]
+        );
+
+        let matches = find_matches(&app_state, file_index, &patterns[..1], None, 1, &cancel);
+        assert_eq!(
+            matches
+                .unwrap()
+                .iter()
+                .map(|m| m.offset)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn edit_from_an_earlier_revision_is_a_conflict() {
+        let app_state = AppState::default();
+        let file_index =
+            open_file_state(temp_file("revision", b"aaaa"), false, &app_state).unwrap();
+        let patterns = vec![(BytePattern::parse("61 61").unwrap(), None)];
+        let offsets = HashSet::from([2]);
+        let cancel = AtomicBool::new(false);
+
+        // Matches outside the requested positions still decide which matches overlap.
+        let matches = find_matches(
+            &app_state,
+            file_index,
+            &patterns,
+            Some(&offsets),
+            1,
+            &cancel,
+        );
+        assert_eq!(
+            matches
+                .unwrap()
+                .iter()
+                .map(|m| m.offset)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+
+        let revision = app_state
+            .with_file(file_index, |file_state| Ok(file_state.revision))
+            .unwrap();
+        edit_file(file_index, &app_state, |journal| journal.insert(0, vec![0])).unwrap();
+
+        assert_eq!(
+            edit_file_revision(file_index, &app_state, Some(revision), |journal| {
+                journal.replace_ranges(&[(2, 2)], b"b")
+            })
+            .err(),
+            Some(AppError::Conflict)
+        );
+        assert_eq!(
+            read_file_window(file_index, 0, None, &app_state).ok(),
+            Some(b"\0aaaa".to_vec())
+        );
+    }
+
+    #[test]
+    fn missing_file_is_not_found() {
+        let app_state = AppState::default();
+        let file_name = temp_file("missing", b"") + ".none";
+
+        assert!(matches!(
+            open_file_state(file_name, false, &app_state),
+            Err(AppError::NotFound(_))
+        ));
     }
 }
@@ -0,0 +1,557 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::file_source::FileSource;
+use std::sync::Arc;
+
+/// The buffer a piece of the document refers to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PieceSource {
+    /// The file on disk.
+    Original,
+    /// The in-memory append buffer holding all the bytes ever written into the document.
+    Added,
+}
+
+/// A contiguous run of bytes in the document taken from one of the source buffers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Piece {
+    source: PieceSource,
+    start: u64,
+    length: u64,
+}
+
+/// A node of the piece tree. The tree is a treap ordered by document position and balanced by
+/// random node priorities; each node knows the length of its subtree so a position is found in
+/// O(log n). Nodes are immutable and shared between versions of the document.
+struct Node {
+    piece: Piece,
+    priority: u64,
+    length: u64,
+    left: Tree,
+    right: Tree,
+}
+
+/// A version of the document as a tree of pieces; `None` is the empty document.
+type Tree = Option<Arc<Node>>;
+
+/// A replacement of a range of the document. The change holds the document versions before and
+/// after the replacement, so applying and reverting it only switches the version. A change is
+/// applied to the document it was reverted from and vice versa, which the undo and redo order
+/// of the edit journal guarantees. The append buffer is never truncated so the change stays
+/// valid any number of times.
+#[derive(Clone)]
+pub struct PieceChange {
+    before: Tree,
+    after: Tree,
+}
+
+/// A piece table document model over a file on disk. The document is a sequence of pieces
+/// referring either to the original file or to an append-only buffer of new bytes, so edits
+/// only split and replace pieces and never move the file data. The pieces are kept in a
+/// persistent balanced tree so an edit costs O(log n) for n pieces.
+pub struct PieceTable {
+    added: Vec<u8>,
+    root: Tree,
+    seed: u64,
+}
+
+impl PieceTable {
+    /// Creates a new piece table for an unmodified file.
+    ///
+    /// # Arguments
+    /// * `original_size` - the size of the file on disk.
+    pub fn new(original_size: u64) -> Self {
+        let mut table = Self {
+            added: Vec::new(),
+            root: None,
+            seed: 0,
+        };
+
+        if original_size > 0 {
+            table.root = table.leaf(Piece {
+                source: PieceSource::Original,
+                start: 0,
+                length: original_size,
+            });
+        }
+
+        table
+    }
+
+    /// Gets the size of the document.
+    pub fn size(&self) -> u64 {
+        length(&self.root)
+    }
+
+    /// Replaces a range of the document with new bytes. Inserting and deleting are
+    /// replacements with an empty range or empty data respectively.
+    /// The caller is responsible for the range being inside the document.
+    ///
+    /// # Arguments
+    /// * `offset` - the position of the range to replace.
+    /// * `length` - the length of the range to replace.
+    /// * `data` - the bytes to replace the range with.
+    ///
+    /// # Returns
+    /// The applied change which can be used to revert the replacement.
+    pub fn replace(&mut self, offset: u64, length: u64, data: &[u8]) -> PieceChange {
+        self.replace_ranges(&[(offset, length)], data)
+    }
+
+    /// Replaces multiple ranges of the document with the same bytes in a single pass over the
+    /// tree; the bytes are written into the append buffer once and shared by the replacements.
+    /// The caller is responsible for the ranges being inside the document, in ascending order
+    /// and not overlapping.
+    ///
+    /// # Arguments
+    /// * `ranges` - the positions and lengths of the ranges to replace.
+    /// * `data` - the bytes to replace each range with.
+    ///
+    /// # Returns
+    /// The applied change which can be used to revert all the replacements.
+    pub fn replace_ranges(&mut self, ranges: &[(u64, u64)], data: &[u8]) -> PieceChange {
+        let before = self.root.clone();
+        let inserted = self.append(data);
+
+        let mut done = None;
+        let mut rest = before.clone();
+        let mut rest_start = 0;
+        for (offset, length) in ranges {
+            let (left, right) = self.split(&rest, offset - rest_start);
+            let (_, right) = self.split(&right, *length);
+            done = merge(done, left);
+            if let Some(piece) = inserted {
+                done = self.push_piece(done, piece);
+            }
+            rest = right;
+            rest_start = offset + length;
+        }
+
+        self.root = merge(done, rest);
+        PieceChange {
+            before,
+            after: self.root.clone(),
+        }
+    }
+
+    /// Applies a change to the document.
+    pub fn apply(&mut self, change: &PieceChange) {
+        self.root = change.after.clone();
+    }
+
+    /// Reverts a change applied to the document.
+    pub fn revert(&mut self, change: &PieceChange) {
+        self.root = change.before.clone();
+    }
+
+    /// Reads bytes from the document.
+    ///
+    /// # Arguments
+    /// * `file` - the original file.
+    /// * `pos` - the position in the document to start reading from.
+    /// * `buffer` - the buffer to read the bytes into.
+    ///
+    /// # Returns
+    /// The amount of bytes read; this is less than the buffer length at the end of the document
+    /// or if the original file was truncated by another process.
+    pub fn read(
+        &self,
+        file: &mut FileSource,
+        pos: u64,
+        buffer: &mut [u8],
+    ) -> std::io::Result<usize> {
+        let size = self.size();
+        if pos >= size {
+            return Ok(0);
+        }
+
+        let count = buffer.len().min((size - pos) as usize);
+        match &self.root {
+            Some(root) => self.read_node(root, file, pos, &mut buffer[..count]),
+            None => Ok(0),
+        }
+    }
+
+    /// Reads bytes from a subtree; the caller is responsible for the range being inside it.
+    ///
+    /// # Returns
+    /// The amount of bytes read; this is less than the buffer length only if the original
+    /// file was truncated by another process.
+    fn read_node(
+        &self,
+        node: &Node,
+        file: &mut FileSource,
+        pos: u64,
+        buffer: &mut [u8],
+    ) -> std::io::Result<usize> {
+        let left_length = length(&node.left);
+        let mut done = 0;
+
+        if let Some(left) = node.left.as_deref().filter(|_| pos < left_length) {
+            let take = buffer.len().min((left_length - pos) as usize);
+            done = self.read_node(left, file, pos, &mut buffer[..take])?;
+            if done < take {
+                return Ok(done);
+            }
+        }
+
+        if done == buffer.len() {
+            return Ok(done);
+        }
+
+        let piece = node.piece;
+        let skip = pos + done as u64 - left_length;
+        if skip < piece.length {
+            let take = ((piece.length - skip) as usize).min(buffer.len() - done);
+            let target = &mut buffer[done..done + take];
+
+            match piece.source {
+                PieceSource::Original => {
+                    let read = file.read_at(piece.start + skip, target)?;
+                    if read < take {
+                        // The file was truncated by another process.
+                        return Ok(done + read);
+                    }
+                }
+                PieceSource::Added => {
+                    let start = (piece.start + skip) as usize;
+                    target.copy_from_slice(&self.added[start..start + take]);
+                }
+            }
+
+            done += take;
+        }
+
+        if let Some(right) = node.right.as_deref().filter(|_| done < buffer.len()) {
+            let pos = pos + done as u64 - left_length - piece.length;
+            done += self.read_node(right, file, pos, &mut buffer[done..])?;
+        }
+
+        Ok(done)
+    }
+
+    /// Writes bytes into the append buffer.
+    ///
+    /// # Returns
+    /// The piece referring to the bytes or `None` if there were no bytes.
+    fn append(&mut self, data: &[u8]) -> Option<Piece> {
+        if data.is_empty() {
+            return None;
+        }
+
+        let piece = Piece {
+            source: PieceSource::Added,
+            start: self.added.len() as u64,
+            length: data.len() as u64,
+        };
+        self.added.extend_from_slice(data);
+        Some(piece)
+    }
+
+    /// Adds a piece to the end of a tree.
+    fn push_piece(&mut self, tree: Tree, piece: Piece) -> Tree {
+        // Bytes typed one after another continue the previous piece of the append buffer.
+        if let Some(last) = last_piece(&tree) {
+            if last.source == PieceSource::Added && last.start + last.length == piece.start {
+                let (head, _) = self.split(&tree, length(&tree) - last.length);
+                let piece = Piece {
+                    length: last.length + piece.length,
+                    ..last
+                };
+                return merge(head, self.leaf(piece));
+            }
+        }
+
+        let leaf = self.leaf(piece);
+        merge(tree, leaf)
+    }
+
+    /// Splits a tree at a document position, splitting the piece containing the position in two.
+    ///
+    /// # Returns
+    /// The trees before and after the position.
+    fn split(&mut self, tree: &Tree, pos: u64) -> (Tree, Tree) {
+        let node = match tree {
+            Some(node) => node,
+            None => return (None, None),
+        };
+
+        let left_length = length(&node.left);
+        let piece_end = left_length + node.piece.length;
+
+        if pos <= left_length {
+            let (left, right) = self.split(&node.left, pos);
+            (
+                left,
+                with_children(node, node.piece, right, node.right.clone()),
+            )
+        } else if pos >= piece_end {
+            let (left, right) = self.split(&node.right, pos - piece_end);
+            (
+                with_children(node, node.piece, node.left.clone(), left),
+                right,
+            )
+        } else {
+            let cut = pos - left_length;
+            let head = Piece {
+                length: cut,
+                ..node.piece
+            };
+            let tail = Piece {
+                start: node.piece.start + cut,
+                length: node.piece.length - cut,
+                ..node.piece
+            };
+
+            // The tail gets a priority of its own to keep the tree balanced.
+            let tail = self.leaf(tail);
+            (
+                with_children(node, head, node.left.clone(), None),
+                merge(tail, node.right.clone()),
+            )
+        }
+    }
+
+    /// Creates a single piece tree with a new random priority.
+    fn leaf(&mut self, piece: Piece) -> Tree {
+        // SplitMix64; the priorities only need to be independent of the edits.
+        self.seed = self.seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut priority = self.seed;
+        priority = (priority ^ (priority >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        priority = (priority ^ (priority >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        priority ^= priority >> 31;
+
+        Some(Arc::new(Node {
+            piece,
+            priority,
+            length: piece.length,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+/// Gets the document length of a tree.
+fn length(tree: &Tree) -> u64 {
+    tree.as_ref().map_or(0, |node| node.length)
+}
+
+/// Gets the last piece of a tree.
+fn last_piece(tree: &Tree) -> Option<Piece> {
+    let mut node = tree.as_deref()?;
+    while let Some(right) = node.right.as_deref() {
+        node = right;
+    }
+    Some(node.piece)
+}
+
+/// Creates a copy of a node with a new piece and children.
+fn with_children(node: &Node, piece: Piece, left: Tree, right: Tree) -> Tree {
+    Some(Arc::new(Node {
+        piece,
+        priority: node.priority,
+        length: length(&left) + piece.length + length(&right),
+        left,
+        right,
+    }))
+}
+
+/// Concatenates two trees.
+fn merge(left: Tree, right: Tree) -> Tree {
+    match (left, right) {
+        (None, tree) | (tree, None) => tree,
+        (Some(left), Some(right)) => {
+            if left.priority >= right.priority {
+                let right = merge(left.right.clone(), Some(right));
+                with_children(&left, left.piece, left.left.clone(), right)
+            } else {
+                let left = merge(Some(left), right.left.clone());
+                with_children(&right, right.piece, left, right.right.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a file source over a file with the specified contents in the temporary directory.
+    fn temp_source(name: &str, data: &[u8]) -> FileSource {
+        let path = std::env::temp_dir().join(format!("hexpt-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        FileSource::new(std::fs::File::open(path).unwrap(), false)
+    }
+
+    /// Reads the whole document.
+    fn contents(table: &PieceTable, file: &mut FileSource) -> Vec<u8> {
+        let mut buffer = vec![0; table.size() as usize];
+        assert_eq!(table.read(file, 0, &mut buffer).unwrap(), buffer.len());
+        buffer
+    }
+
+    /// Gets the pieces of the document in order.
+    fn pieces(table: &PieceTable) -> Vec<Piece> {
+        fn collect(tree: &Tree, pieces: &mut Vec<Piece>) {
+            if let Some(node) = tree {
+                collect(&node.left, pieces);
+                pieces.push(node.piece);
+                collect(&node.right, pieces);
+            }
+        }
+
+        let mut pieces = Vec::new();
+        collect(&table.root, &mut pieces);
+        pieces
+    }
+
+    #[test]
+    fn insert_splits_the_original_piece() {
+        let mut file = temp_source("split", b"abcdef");
+        let mut table = PieceTable::new(6);
+
+        table.replace(3, 0, b"XY");
+
+        assert_eq!(contents(&table, &mut file), b"abcXYdef");
+        assert_eq!(
+            pieces(&table)
+                .iter()
+                .map(|p| (p.source, p.start, p.length))
+                .collect::<Vec<_>>(),
+            vec![
+                (PieceSource::Original, 0, 3),
+                (PieceSource::Added, 0, 2),
+                (PieceSource::Original, 3, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn typed_bytes_continue_the_previous_piece() {
+        let mut file = temp_source("merge", b"abcdef");
+        let mut table = PieceTable::new(6);
+
+        for (i, byte) in b"XYZ".iter().enumerate() {
+            table.replace(2 + i as u64, 0, &[*byte]);
+        }
+
+        assert_eq!(contents(&table, &mut file), b"abXYZcdef");
+        assert_eq!(pieces(&table).len(), 3);
+
+        // An insert elsewhere doesn't continue the piece.
+        table.replace(0, 0, b"Q");
+        table.replace(6, 0, b"R");
+        assert_eq!(contents(&table, &mut file), b"QabXYZRcdef");
+        assert_eq!(pieces(&table).len(), 5);
+    }
+
+    #[test]
+    fn changes_revert_and_apply_in_order() {
+        let mut file = temp_source("round-trip", b"0123456789");
+        let mut table = PieceTable::new(10);
+
+        let changes = [
+            table.replace(2, 3, b"ab"),
+            table.replace(0, 1, &[]),
+            table.replace(8, 0, b"cd"),
+            table.replace(4, 4, b"efgh"),
+        ];
+        let edited = contents(&table, &mut file);
+        assert_eq!(edited, b"1ab5efghcd");
+
+        changes.iter().rev().for_each(|change| table.revert(change));
+        assert_eq!(contents(&table, &mut file), b"0123456789");
+
+        changes.iter().for_each(|change| table.apply(change));
+        assert_eq!(contents(&table, &mut file), edited);
+    }
+
+    #[test]
+    fn replace_ranges_is_a_single_change() {
+        let mut file = temp_source("ranges", b"a--b--c--d");
+        let mut table = PieceTable::new(10);
+
+        let change = table.replace_ranges(&[(1, 2), (4, 2), (7, 2)], b"+");
+        assert_eq!(contents(&table, &mut file), b"a+b+c+d");
+        assert_eq!(table.added, b"+");
+
+        table.revert(&change);
+        assert_eq!(contents(&table, &mut file), b"a--b--c--d");
+        table.apply(&change);
+        assert_eq!(contents(&table, &mut file), b"a+b+c+d");
+
+        // Adjacent ranges and removals.
+        table.replace_ranges(&[(0, 1), (1, 1), (6, 1)], &[]);
+        assert_eq!(contents(&table, &mut file), b"b+c+");
+    }
+
+    #[test]
+    fn reads_span_piece_boundaries() {
+        let original = (0..=255).collect::<Vec<u8>>();
+        let mut file = temp_source("boundaries", &original);
+        let mut table = PieceTable::new(original.len() as u64);
+        let mut expected = original.clone();
+
+        // A deterministic series of edits compared against a plain vector.
+        let mut seed = 7u64;
+        let mut next = |bound: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % bound.max(1)
+        };
+        for _ in 0..500 {
+            let offset = next(expected.len() as u64 + 1);
+            let length = next(4).min(expected.len() as u64 - offset);
+            let data = vec![next(256) as u8; next(8) as usize];
+
+            table.replace(offset, length, &data);
+            let range = offset as usize..(offset + length) as usize;
+            expected.splice(range, data);
+        }
+
+        assert_eq!(table.size(), expected.len() as u64);
+        for pos in 0..expected.len() {
+            let mut buffer = [0; 37];
+            let read = table.read(&mut file, pos as u64, &mut buffer).unwrap();
+            let end = expected.len().min(pos + buffer.len());
+            assert_eq!(&buffer[..read], &expected[pos..end]);
+        }
+    }
+
+    #[test]
+    fn truncated_original_ends_the_read() {
+        let mut table = PieceTable::new(8);
+        table.replace(2, 0, b"XY");
+
+        let mut file = temp_source("truncated", b"abc");
+        let mut buffer = [0; 10];
+
+        assert_eq!(table.read(&mut file, 0, &mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"abXYc");
+        assert_eq!(table.read(&mut file, 4, &mut buffer).unwrap(), 1);
+        assert_eq!(table.read(&mut file, 10, &mut buffer).unwrap(), 0);
+    }
+}
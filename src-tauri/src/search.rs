@@ -0,0 +1,475 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::atomic::AtomicBool};
+use tauri::{Emitter, Manager};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, scan_file},
+    string_encodings::{encode_char, TextEncoding},
+    tasks::TaskContext,
+    types::AppState,
+};
+
+/// The event emitted with the matches found by a search task.
+pub const SEARCH_MATCHES_EVENT: &str = "search-matches";
+
+/// The maximum length of a regular expression match unless requested otherwise.
+pub const DEFAULT_MAX_REGEX_MATCH_LENGTH: usize = 4096;
+
+/// The upper limit of the maximum length of a regular expression match; the limit is the
+/// amount of bytes the read chunks overlap by, which are kept in memory.
+pub const MAX_REGEX_MATCH_LENGTH: usize = 1024 * 1024;
+
+/// The amount of bytes before a read chunk a regular expression search sees, so assertions such
+/// as `^`, `\A` and `\b` at the start of the chunk look at the real preceding bytes; this covers
+/// the longest UTF-8 character.
+const REGEX_LOOK_BEHIND: usize = 4;
+
+/// The maximum amount of matches a search reports unless requested otherwise.
+pub const DEFAULT_MAX_SEARCH_RESULTS: usize = 100_000;
+
+/// A match found by a search.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub offset: u64,
+    pub length: u64,
+    /// The encoding of the match in a text search.
+    pub encoding: Option<TextEncoding>,
+}
+
+/// A byte or text search query.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchQuery {
+    /// A hexadecimal byte pattern with `?` wildcards.
+    Bytes { pattern: String },
+    /// Text encoded in one or more text encodings.
+    Text {
+        text: String,
+        encodings: Vec<TextEncoding>,
+        case_sensitive: bool,
+    },
+}
+
+impl SearchQuery {
+    /// Gets the byte patterns to search for with the text encoding reported with their matches.
+    pub fn patterns(&self) -> Result<Vec<(BytePattern, Option<TextEncoding>)>, AppError> {
+        match self {
+            SearchQuery::Bytes { pattern } => Ok(vec![(BytePattern::parse(pattern)?, None)]),
+            SearchQuery::Text {
+                text,
+                encodings,
+                case_sensitive,
+            } => text_patterns(text, encodings, *case_sensitive),
+        }
+    }
+}
+
+/// A batch of matches found by a search task.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SearchMatches {
+    pub task_id: u64,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// A byte pattern where each byte is compared through a mask, so single nibbles or whole
+/// bytes can be left as wildcards.
+pub struct BytePattern {
+    values: Vec<u8>,
+    masks: Vec<u8>,
+}
+
+impl BytePattern {
+    /// Parses a hexadecimal pattern such as `4D 5A ?? 00 ?F`. Whitespace is ignored and
+    /// a `?` in place of a hex digit matches any nibble.
+    ///
+    /// # Arguments
+    /// * `pattern` - the pattern to parse.
+    ///
+    /// # Returns
+    /// The parsed pattern or an error if the pattern is not valid.
+    pub fn parse(pattern: &str) -> Result<Self, AppError> {
+        let digits: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        if digits.is_empty() || digits.len() % 2 == 1 {
+            return Err(AppError::InvalidArgument(
+                "Invalid search pattern".to_string(),
+            ));
+        }
+
+        let mut values = Vec::new();
+        let mut masks = Vec::new();
+
+        for pair in digits.chunks(2) {
+            let mut value = 0;
+            let mut mask = 0;
+            for digit in pair {
+                value <<= 4;
+                mask <<= 4;
+                if *digit != '?' {
+                    value |= digit.to_digit(16).ok_or_else(|| {
+                        AppError::InvalidArgument("Invalid search pattern".to_string())
+                    })? as u8;
+                    mask |= 0x0f;
+                }
+            }
+
+            values.push(value);
+            masks.push(mask);
+        }
+
+        Ok(Self { values, masks })
+    }
+
+    /// Creates a pattern from text in the specified encoding. When the search is case-insensitive
+    /// the case bit of the bytes holding ASCII letters is left out of the comparison.
+    ///
+    /// # Arguments
+    /// * `text` - the text to search for.
+    /// * `encoding` - the encoding to search the text in.
+    /// * `case_sensitive` - whether the letter case must match.
+    ///
+    /// # Returns
+    /// The pattern or `None` if the text is empty or can't be represented in the encoding.
+    pub fn from_text(text: &str, encoding: TextEncoding, case_sensitive: bool) -> Option<Self> {
+        let mut values = Vec::new();
+        let mut masks = Vec::new();
+
+        for c in text.chars() {
+            let bytes = encode_char(c, encoding)?;
+            let fold = !case_sensitive && c.is_ascii_alphabetic();
+            for byte in bytes {
+                match fold && byte == c as u8 {
+                    true => {
+                        values.push(byte & 0xdf);
+                        masks.push(0xdf);
+                    }
+                    false => {
+                        values.push(byte);
+                        masks.push(0xff);
+                    }
+                }
+            }
+        }
+
+        match values.is_empty() {
+            true => None,
+            false => Some(Self { values, masks }),
+        }
+    }
+
+    /// Gets the length of the pattern in bytes.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks whether the pattern matches the data at a position.
+    ///
+    /// # Arguments
+    /// * `data` - the data to check.
+    /// * `pos` - the position in the data to check the pattern at.
+    ///
+    /// # Returns
+    /// `true` if the pattern matches; `false` if it doesn't or the data ends before the pattern.
+    pub fn matches_at(&self, data: &[u8], pos: usize) -> bool {
+        match data.get(pos..pos + self.len()) {
+            Some(bytes) => self
+                .values
+                .iter()
+                .zip(&self.masks)
+                .zip(bytes)
+                .all(|((value, mask), byte)| byte & mask == *value),
+            None => false,
+        }
+    }
+}
+
+/// Creates the byte patterns to search text in the specified encodings with.
+///
+/// # Arguments
+/// * `text` - the text to search for.
+/// * `encodings` - the encodings to search the text in.
+/// * `case_sensitive` - whether the letter case must match.
+///
+/// # Returns
+/// The patterns with their encodings or an error if the text can't be searched in any of the encodings.
+pub fn text_patterns(
+    text: &str,
+    encodings: &[TextEncoding],
+    case_sensitive: bool,
+) -> Result<Vec<(BytePattern, Option<TextEncoding>)>, AppError> {
+    let patterns: Vec<(BytePattern, Option<TextEncoding>)> = encodings
+        .iter()
+        .filter_map(|encoding| {
+            BytePattern::from_text(text, *encoding, case_sensitive)
+                .map(|pattern| (pattern, Some(*encoding)))
+        })
+        .collect();
+
+    match patterns.is_empty() {
+        true => Err(AppError::InvalidArgument("Invalid search text".to_string())),
+        false => Ok(patterns),
+    }
+}
+
+/// Finds the matches of byte patterns in the whole file chunk by chunk. The matches don't
+/// overlap: where matches overlap the one starting first is kept, and of matches starting at
+/// the same position the one of the pattern listed first.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file to search.
+/// * `patterns` - the patterns to search for with the text encoding reported with their matches.
+/// * `offsets` - the positions of the matches to report; all matches are reported if not specified.
+///   The other matches still take part in the overlap rule.
+/// * `max_results` - the maximum amount of matches to report.
+/// * `cancel` - the cancellation flag checked between the read chunks.
+/// * `on_matches` - called after each read chunk with the matches found in it in ascending
+///   order, the position the file has been searched up to and the size of the file.
+///
+/// # Returns
+/// `Ok(())` if the search completed, was stopped or cancelled; an error otherwise.
+fn scan_patterns<F>(
+    app_state: &AppState,
+    file_index: usize,
+    patterns: &[(BytePattern, Option<TextEncoding>)],
+    offsets: Option<&HashSet<u64>>,
+    max_results: usize,
+    cancel: &AtomicBool,
+    mut on_matches: F,
+) -> Result<(), AppError>
+where
+    F: FnMut(Vec<SearchMatch>, u64, u64),
+{
+    let total = file_size(app_state, file_index)?;
+    let overlap = patterns.iter().map(|p| p.0.len()).max().unwrap_or(1) - 1;
+    let mut found = 0;
+    let mut last_match_end = 0;
+
+    scan_file(app_state, file_index, 0..total, overlap, cancel, |chunk| {
+        // The positions are checked in order and the first pattern matching at a position wins,
+        // so only the reported matches are kept in memory.
+        let mut matches = Vec::new();
+        for i in 0..chunk.length {
+            let offset = chunk.pos + i as u64;
+            if found + matches.len() >= max_results {
+                break;
+            }
+
+            if offset < last_match_end {
+                continue;
+            }
+
+            let matched = patterns
+                .iter()
+                .find(|(pattern, _)| pattern.matches_at(chunk.data, i));
+            if let Some((pattern, encoding)) = matched {
+                last_match_end = offset + pattern.len() as u64;
+                if offsets.is_none_or(|offsets| offsets.contains(&offset)) {
+                    matches.push(SearchMatch {
+                        offset,
+                        length: pattern.len() as u64,
+                        encoding: *encoding,
+                    });
+                }
+            }
+        }
+
+        found += matches.len();
+        on_matches(matches, chunk.pos + chunk.length as u64, total);
+        found < max_results
+    })?;
+
+    Ok(())
+}
+
+/// Finds the matches of byte patterns in the whole file with the overlap rule of `scan_patterns`.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file to search.
+/// * `patterns` - the patterns to search for with the text encoding reported with their matches.
+/// * `offsets` - the positions of the matches to find; all matches are found if not specified.
+/// * `max_results` - the maximum amount of matches to find.
+/// * `cancel` - the cancellation flag checked between the read chunks.
+///
+/// # Returns
+/// The matches in ascending order.
+pub fn find_matches(
+    app_state: &AppState,
+    file_index: usize,
+    patterns: &[(BytePattern, Option<TextEncoding>)],
+    offsets: Option<&HashSet<u64>>,
+    max_results: usize,
+    cancel: &AtomicBool,
+) -> Result<Vec<SearchMatch>, AppError> {
+    let mut result = Vec::new();
+    scan_patterns(
+        app_state,
+        file_index,
+        patterns,
+        offsets,
+        max_results,
+        cancel,
+        |matches, _, _| result.extend(matches),
+    )?;
+
+    Ok(result)
+}
+
+/// Searches the whole file for byte patterns, emitting the matches in batches per read chunk.
+/// The matches follow the overlap rule of `scan_patterns`, so a search reports the same matches
+/// a replace replaces.
+///
+/// # Arguments
+/// * `context` - the context of the search task.
+/// * `file_index` - the index of the file to search.
+/// * `patterns` - the patterns to search for with the text encoding reported with their matches.
+/// * `max_results` - the maximum amount of matches to report.
+///
+/// # Returns
+/// `Ok(())` if the search completed or was cancelled; an error otherwise.
+pub fn search_patterns(
+    context: &TaskContext,
+    file_index: usize,
+    patterns: &[(BytePattern, Option<TextEncoding>)],
+    max_results: usize,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    scan_patterns(
+        &app_state,
+        file_index,
+        patterns,
+        None,
+        max_results,
+        context.cancel_flag(),
+        |matches, pos, total| {
+            emit_matches(context, matches);
+            context.progress(pos, total);
+        },
+    )
+}
+
+/// Searches the whole file with a byte-oriented regular expression, emitting the matches in
+/// batches per read chunk. The chunks overlap by the maximum match length, so matches up to
+/// that length spanning two chunks are found; longer matches are cut at the overlap. The bytes
+/// preceding a chunk are searched as context, so look-behind assertions work across chunks.
+///
+/// # Arguments
+/// * `context` - the context of the search task.
+/// * `file_index` - the index of the file to search.
+/// * `regex` - the regular expression to search with.
+/// * `max_match_length` - the maximum length of a match.
+/// * `max_results` - the maximum amount of matches to report.
+///
+/// # Returns
+/// `Ok(())` if the search completed or was cancelled; an error otherwise.
+pub fn search_regex(
+    context: &TaskContext,
+    file_index: usize,
+    regex: &Regex,
+    max_match_length: usize,
+    max_results: usize,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    let total = file_size(&app_state, file_index)?;
+    let mut found = 0;
+    let mut last_match_end = 0;
+    let mut haystack = Vec::new();
+    let mut look_behind = 0;
+
+    scan_file(
+        &app_state,
+        file_index,
+        0..total,
+        max_match_length,
+        context.cancel_flag(),
+        |chunk| {
+            // The haystack holds the tail of the previous chunk followed by this chunk.
+            haystack.drain(..haystack.len() - look_behind);
+            haystack.extend_from_slice(chunk.data);
+
+            let mut matches = Vec::new();
+            let mut start = look_behind;
+            while start <= haystack.len() {
+                let m = match regex.find_at(&haystack, start) {
+                    Some(m) => m,
+                    None => break,
+                };
+
+                let chunk_start = m.start() - look_behind;
+                if chunk_start >= chunk.length || found + matches.len() >= max_results {
+                    break;
+                }
+
+                start = match m.is_empty() {
+                    true => m.end() + 1,
+                    false => m.end(),
+                };
+
+                // The overlap may contain the tail of a match reported with the previous chunk.
+                let offset = chunk.pos + chunk_start as u64;
+                if offset < last_match_end || m.is_empty() {
+                    continue;
+                }
+
+                last_match_end = offset + m.len() as u64;
+                matches.push(SearchMatch {
+                    offset,
+                    length: m.len() as u64,
+                    encoding: None,
+                });
+            }
+
+            // Only the bytes before the start of the next chunk are kept as its context.
+            let chunk_end = look_behind + chunk.length;
+            haystack.truncate(chunk_end);
+            look_behind = chunk_end.min(REGEX_LOOK_BEHIND);
+
+            found += matches.len();
+            emit_matches(context, matches);
+
+            context.progress(chunk.pos + chunk.length as u64, total);
+            found < max_results
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Emits a batch of matches found by a search task.
+pub fn emit_matches(context: &TaskContext, matches: Vec<SearchMatch>) {
+    if !matches.is_empty() {
+        let _ = context.app.emit(
+            SEARCH_MATCHES_EVENT,
+            SearchMatches {
+                task_id: context.task_id,
+                matches,
+            },
+        );
+    }
+}
@@ -11,6 +11,106 @@ pub struct TextDataInPosition {
     pub text_be_utf32: String,
 }
 
+/// The text encodings the text can be searched with.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+/// Encodes a character into the bytes of the specified encoding.
+///
+/// # Arguments
+/// * `c` - the character to encode.
+/// * `encoding` - the encoding to use.
+///
+/// # Returns
+/// The encoded bytes or `None` if the character can't be represented in the encoding.
+pub fn encode_char(c: char, encoding: TextEncoding) -> Option<Vec<u8>> {
+    let mut units = [0; 2];
+    match encoding {
+        TextEncoding::Ascii if c.is_ascii() => Some(vec![c as u8]),
+        TextEncoding::Ascii => None,
+        TextEncoding::Utf8 => Some(c.to_string().into_bytes()),
+        TextEncoding::Utf16Le => Some(
+            c.encode_utf16(&mut units)
+                .iter()
+                .flat_map(|u| u.to_le_bytes())
+                .collect(),
+        ),
+        TextEncoding::Utf16Be => Some(
+            c.encode_utf16(&mut units)
+                .iter()
+                .flat_map(|u| u.to_be_bytes())
+                .collect(),
+        ),
+        TextEncoding::Utf32Le => Some((c as u32).to_le_bytes().to_vec()),
+        TextEncoding::Utf32Be => Some((c as u32).to_be_bytes().to_vec()),
+    }
+}
+
+/// Decodes the character at the start of the bytes in the specified encoding.
+///
+/// # Arguments
+/// * `data` - the encoded bytes.
+/// * `encoding` - the encoding to use.
+///
+/// # Returns
+/// The character and the amount of bytes it takes or `None` if the bytes don't start with a
+/// complete valid character.
+pub fn decode_char(data: &[u8], encoding: TextEncoding) -> Option<(char, usize)> {
+    match encoding {
+        TextEncoding::Ascii => match data.first() {
+            Some(b) if b.is_ascii() => Some((*b as char, 1)),
+            _ => None,
+        },
+        TextEncoding::Utf8 => {
+            let length = match *data.first()? {
+                0x00..=0x7F => 1,
+                0xC2..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF4 => 4,
+                _ => return None,
+            };
+            let c = std::str::from_utf8(data.get(..length)?)
+                .ok()?
+                .chars()
+                .next()?;
+            Some((c, length))
+        }
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let unit = |i: usize| -> Option<u16> {
+                let bytes: [u8; 2] = data.get(i..i + 2)?.try_into().ok()?;
+                match encoding {
+                    TextEncoding::Utf16Le => Some(u16::from_le_bytes(bytes)),
+                    _ => Some(u16::from_be_bytes(bytes)),
+                }
+            };
+            let first = unit(0)?;
+            match first {
+                0xD800..=0xDBFF => {
+                    let c = char::decode_utf16([first, unit(2)?]).next()?.ok()?;
+                    Some((c, 4))
+                }
+                _ => Some((char::from_u32(first as u32)?, 2)),
+            }
+        }
+        TextEncoding::Utf32Le | TextEncoding::Utf32Be => {
+            let bytes: [u8; 4] = data.get(..4)?.try_into().ok()?;
+            let value = match encoding {
+                TextEncoding::Utf32Le => u32::from_le_bytes(bytes),
+                _ => u32::from_be_bytes(bytes),
+            };
+            Some((char::from_u32(value)?, 4))
+        }
+    }
+}
+
 pub fn bytes_to_ascii(bytes: &[u8]) -> String {
     let mut result = String::new();
     for i in 0..bytes.len() {
@@ -23,7 +123,7 @@ pub fn bytes_to_utf8(bytes: &[u8], big_endian: bool) -> String {
     let mut result = String::new();
 
     let mut last_skip = false;
-    for i in 0..bytes.len() - 2 {
+    for i in 0..bytes.len().saturating_sub(2) {
         if last_skip {
             last_skip = false;
             continue;
@@ -103,7 +203,7 @@ pub fn bytes_to_utf8(bytes: &[u8], big_endian: bool) -> String {
 
 pub fn bytes_to_utf16(bytes: &[u8], big_endian: bool) -> String {
     let mut result = String::new();
-    for i in 0..bytes.len() - 2 {
+    for i in 0..bytes.len().saturating_sub(2) {
         let buffer16: [u8; 2] = if big_endian {
             [bytes[i], bytes[i + 1]]
         } else {
@@ -121,7 +221,7 @@ pub fn bytes_to_utf16(bytes: &[u8], big_endian: bool) -> String {
 
 pub fn bytes_to_utf32(bytes: &[u8], big_endian: bool) -> String {
     let mut result = String::new();
-    for i in 0..bytes.len() - 4 {
+    for i in 0..bytes.len().saturating_sub(4) {
         let buffer32: [u8; 4] = if big_endian {
             [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]
         } else {
@@ -0,0 +1,291 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, scan_file, ScanChunk},
+    string_encodings::{decode_char, TextEncoding},
+    tasks::TaskContext,
+    types::AppState,
+};
+
+/// The event emitted with the strings found by a strings extraction task.
+pub const STRINGS_FOUND_EVENT: &str = "strings-found";
+
+/// The minimum amount of characters in a string unless requested otherwise.
+pub const DEFAULT_MIN_STRING_LENGTH: usize = 4;
+
+/// The maximum amount of characters of a string returned; the length still covers the whole run.
+const MAX_STRING_TEXT_LENGTH: usize = 1024;
+
+/// A run of printable characters found in a file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExtractedString {
+    pub offset: u64,
+    /// The length of the run in bytes.
+    pub length: u64,
+    pub encoding: TextEncoding,
+    pub text: String,
+}
+
+/// A batch of strings found by a strings extraction task.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExtractedStrings {
+    pub task_id: u64,
+    pub strings: Vec<ExtractedString>,
+}
+
+/// A run of printable characters being collected.
+struct StringRun {
+    offset: u64,
+    length: u64,
+    text: String,
+    chars: usize,
+    multi_byte: bool,
+}
+
+/// Collects the runs of printable characters of one encoding starting at one alignment.
+struct StringScanner {
+    encoding: TextEncoding,
+    unit: usize,
+    /// The position of the next character to decode.
+    pos: u64,
+    run: Option<StringRun>,
+    /// Whether runs without multi-byte characters are skipped as they are found as ASCII.
+    skip_single_byte: bool,
+}
+
+impl StringScanner {
+    fn new(encoding: TextEncoding, unit: usize, phase: usize, skip_single_byte: bool) -> Self {
+        Self {
+            encoding,
+            unit,
+            pos: phase as u64,
+            run: None,
+            skip_single_byte,
+        }
+    }
+
+    fn is_printable(&self, c: char) -> bool {
+        let printable = c == '\t' || !c.is_control() && c != char::REPLACEMENT_CHARACTER;
+        match self.encoding {
+            // Most random binary data decodes as CJK characters in UTF-16, so the runs are
+            // limited to the Latin, Greek and Cyrillic blocks.
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => printable && (c as u32) < 0x0530,
+            _ => printable,
+        }
+    }
+
+    /// Decodes the characters starting in the chunk; the last one may continue into the overlap.
+    fn scan(&mut self, chunk: &ScanChunk, min_length: usize, found: &mut Vec<ExtractedString>) {
+        let mut i = (self.pos - chunk.pos) as usize;
+        while i < chunk.length {
+            let decoded =
+                decode_char(&chunk.data[i..], self.encoding).filter(|(c, _)| self.is_printable(*c));
+
+            match decoded {
+                Some((c, size)) => {
+                    let run = self.run.get_or_insert_with(|| StringRun {
+                        offset: chunk.pos + i as u64,
+                        length: 0,
+                        text: String::new(),
+                        chars: 0,
+                        multi_byte: false,
+                    });
+
+                    if run.chars < MAX_STRING_TEXT_LENGTH {
+                        run.text.push(c);
+                    }
+                    run.length += size as u64;
+                    run.chars += 1;
+                    run.multi_byte |= size > 1;
+                    i += size;
+                }
+                None => {
+                    self.finish(min_length, found);
+                    i += self.unit;
+                }
+            }
+        }
+
+        self.pos = chunk.pos + i as u64;
+    }
+
+    /// Ends the current run, keeping it if it is long enough.
+    fn finish(&mut self, min_length: usize, found: &mut Vec<ExtractedString>) {
+        if let Some(run) = self.run.take() {
+            if run.chars >= min_length && (run.multi_byte || !self.skip_single_byte) {
+                found.push(ExtractedString {
+                    offset: run.offset,
+                    length: run.length,
+                    encoding: self.encoding,
+                    text: run.text,
+                });
+            }
+        }
+    }
+}
+
+/// Scans the whole file for runs of printable characters like `strings(1)`, emitting the strings
+/// in batches per read chunk. The wide encodings are scanned at every alignment, and UTF-8 runs
+/// consisting of ASCII only are left to the ASCII scan when both are requested.
+///
+/// # Arguments
+/// * `context` - the context of the extraction task.
+/// * `file_index` - the index of the file to scan.
+/// * `encodings` - the encodings to look for strings in.
+/// * `min_length` - the minimum amount of characters in a string.
+/// * `max_results` - the maximum amount of strings to report.
+///
+/// # Returns
+/// `Ok(())` if the extraction completed or was cancelled; an error otherwise.
+pub fn scan_strings(
+    context: &TaskContext,
+    file_index: usize,
+    encodings: &[TextEncoding],
+    min_length: usize,
+    max_results: usize,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    let total = file_size(&app_state, file_index)?;
+    let skip_single_byte = encodings.contains(&TextEncoding::Ascii);
+    let mut scanners = Vec::new();
+
+    for encoding in encodings {
+        let unit = match encoding {
+            TextEncoding::Ascii | TextEncoding::Utf8 => 1,
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+            TextEncoding::Utf32Le | TextEncoding::Utf32Be => 4,
+        };
+
+        for phase in 0..unit {
+            let skip = skip_single_byte && *encoding == TextEncoding::Utf8;
+            scanners.push(StringScanner::new(*encoding, unit, phase, skip));
+        }
+    }
+
+    let mut found = 0;
+    let completed = scan_file(
+        &app_state,
+        file_index,
+        0..total,
+        3,
+        context.cancel_flag(),
+        |chunk| {
+            let mut strings = Vec::new();
+            for scanner in &mut scanners {
+                scanner.scan(&chunk, min_length, &mut strings);
+            }
+
+            emit_strings(context, strings, &mut found, max_results);
+            context.progress(chunk.pos + chunk.length as u64, total);
+            found < max_results
+        },
+    )?;
+
+    if completed {
+        let mut strings = Vec::new();
+        for scanner in &mut scanners {
+            scanner.finish(min_length, &mut strings);
+        }
+
+        emit_strings(context, strings, &mut found, max_results);
+    }
+
+    Ok(())
+}
+
+/// Emits a batch of strings in the order of their positions.
+fn emit_strings(
+    context: &TaskContext,
+    mut strings: Vec<ExtractedString>,
+    found: &mut usize,
+    max_results: usize,
+) {
+    strings.sort_by_key(|s| s.offset);
+    strings.truncate(max_results - *found);
+    *found += strings.len();
+
+    if !strings.is_empty() {
+        let _ = context.app.emit(
+            STRINGS_FOUND_EVENT,
+            ExtractedStrings {
+                task_id: context.task_id,
+                strings,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scans data split into chunks of the specified length like `scan_file` does, with all
+    /// the alignments of an encoding.
+    fn scan(data: &[u8], chunk_length: usize, encoding: TextEncoding) -> Vec<(u64, u64, String)> {
+        let mut scanners: Vec<StringScanner> = (0..2)
+            .map(|phase| StringScanner::new(encoding, 2, phase, false))
+            .collect();
+        let mut found = Vec::new();
+
+        for pos in (0..data.len()).step_by(chunk_length) {
+            let chunk = ScanChunk {
+                pos: pos as u64,
+                data: &data[pos..data.len().min(pos + chunk_length + 3)],
+                length: chunk_length.min(data.len() - pos),
+            };
+            for scanner in &mut scanners {
+                scanner.scan(&chunk, DEFAULT_MIN_STRING_LENGTH, &mut found);
+            }
+        }
+        for scanner in &mut scanners {
+            scanner.finish(DEFAULT_MIN_STRING_LENGTH, &mut found);
+        }
+
+        found
+            .into_iter()
+            .map(|s| (s.offset, s.length, s.text))
+            .collect()
+    }
+
+    #[test]
+    fn utf16_runs_continue_across_chunks() {
+        let mut data = vec![0xFF, 0xFF, 0xFF];
+        data.extend("Hello wörld".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        data.extend_from_slice(&[0, 0, 0x41, 0, 0x42, 0, 0, 0]);
+
+        for chunk_length in 1..=data.len() {
+            assert_eq!(
+                scan(&data, chunk_length, TextEncoding::Utf16Le),
+                vec![(3, 22, "Hello wörld".to_string())],
+                "chunk length {}",
+                chunk_length
+            );
+        }
+    }
+}
@@ -0,0 +1,169 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{error::AppError, types::AppState};
+
+/// The event emitted while a background task advances.
+pub const TASK_PROGRESS_EVENT: &str = "task-progress";
+/// The event emitted once a background task has stopped.
+pub const TASK_FINISHED_EVENT: &str = "task-finished";
+
+/// The progress of a background task.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TaskProgress {
+    pub task_id: u64,
+    pub position: u64,
+    pub total: u64,
+}
+
+/// The final state of a background task.
+#[derive(Clone, Serialize)]
+pub struct TaskFinished {
+    pub task_id: u64,
+    pub cancelled: bool,
+    pub error: Option<AppError>,
+}
+
+/// The registry of the running background tasks and their cancellation flags.
+#[derive(Default)]
+pub struct TaskRegistry {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+}
+
+impl TaskRegistry {
+    /// Requests a running task to stop.
+    ///
+    /// # Arguments
+    /// * `task_id` - the identifier of the task to cancel.
+    ///
+    /// # Returns
+    /// `true` if the task was running; `false` otherwise.
+    pub fn cancel(&self, task_id: u64) -> bool {
+        match self.running.lock() {
+            Ok(running) => match running.get(&task_id) {
+                Some(cancel) => {
+                    cancel.store(true, Ordering::Relaxed);
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn register(&self) -> (u64, Arc<AtomicBool>) {
+        let task_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        if let Ok(mut running) = self.running.lock() {
+            running.insert(task_id, cancel.clone());
+        }
+
+        (task_id, cancel)
+    }
+
+    fn unregister(&self, task_id: u64) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(&task_id);
+        }
+    }
+}
+
+/// The context of a running background task.
+pub struct TaskContext {
+    pub task_id: u64,
+    pub app: AppHandle,
+    cancel: Arc<AtomicBool>,
+}
+
+impl TaskContext {
+    /// Gets the cancellation flag of the task.
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    /// Emits the progress of the task to the frontend.
+    ///
+    /// # Arguments
+    /// * `position` - the amount of work done.
+    /// * `total` - the total amount of work.
+    pub fn progress(&self, position: u64, total: u64) {
+        let _ = self.app.emit(
+            TASK_PROGRESS_EVENT,
+            TaskProgress {
+                task_id: self.task_id,
+                position,
+                total,
+            },
+        );
+    }
+}
+
+/// Runs a function as a cancellable background task on the blocking thread pool.
+/// The `task-finished` event is emitted once the function returns.
+///
+/// # Arguments
+/// * `app` - the application handle.
+/// * `task` - the function to run.
+///
+/// # Returns
+/// The identifier of the started task.
+pub fn spawn_task<F>(app: AppHandle, task: F) -> u64
+where
+    F: FnOnce(&TaskContext) -> Result<(), AppError> + Send + 'static,
+{
+    let (task_id, cancel) = app.state::<AppState>().tasks.register();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let context = TaskContext {
+            task_id,
+            app,
+            cancel,
+        };
+
+        let error = task(&context).err();
+        context.app.state::<AppState>().tasks.unregister(task_id);
+
+        let _ = context.app.emit(
+            TASK_FINISHED_EVENT,
+            TaskFinished {
+                task_id,
+                cancelled: context.cancel.load(Ordering::Relaxed),
+                error,
+            },
+        );
+    });
+
+    task_id
+}
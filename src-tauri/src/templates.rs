@@ -0,0 +1,575 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, read_file_data},
+    types::AppState,
+    value_search::NumericType,
+};
+
+/// The maximum amount of fields a template can produce.
+const MAX_TEMPLATE_FIELDS: usize = 100_000;
+/// The maximum depth of nested structs, which stops recursive templates.
+const MAX_TEMPLATE_DEPTH: usize = 64;
+/// The maximum amount of bytes shown as the value of a `bytes` field.
+const MAX_BYTES_VALUE_LENGTH: usize = 64;
+/// The maximum amount of bytes decoded as the value of a `string` field.
+const MAX_STRING_VALUE_LENGTH: usize = 4096;
+
+/// The byte order of the numeric fields.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// A size given either as a number or as the name of an earlier integer field of the same struct.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TemplateSize {
+    Fixed(u64),
+    Field(String),
+}
+
+/// A field of a struct template.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FieldTemplate {
+    pub name: String,
+    /// A numeric type such as `u32` or `f64`, `string`, `bytes` or the name of a struct.
+    #[serde(rename = "type")]
+    pub field_type: String,
+    /// The byte order; inherited from the struct if not specified.
+    pub endian: Option<Endianness>,
+    /// The length of a `string` or `bytes` field in bytes.
+    pub length: Option<TemplateSize>,
+    /// Makes the field an array of the specified amount of items.
+    pub count: Option<TemplateSize>,
+}
+
+/// A struct template.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StructTemplate {
+    pub name: String,
+    /// The byte order of the fields; inherited from the parent struct if not specified.
+    pub endian: Option<Endianness>,
+    pub fields: Vec<FieldTemplate>,
+}
+
+/// A set of struct templates stored in a TOML or JSON file, e.g.
+/// ```toml
+/// root = "Header"
+///
+/// [[structs]]
+/// name = "Header"
+/// endian = "little"
+/// fields = [
+///     { name = "magic", type = "bytes", length = 4 },
+///     { name = "count", type = "u16" },
+///     { name = "entries", type = "Entry", count = "count" },
+/// ]
+///
+/// [[structs]]
+/// name = "Entry"
+/// fields = [{ name = "offset", type = "u32", endian = "big" }, { name = "name", type = "string", length = 8 }]
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TemplateFile {
+    /// The struct applied to the data; defaults to the first struct.
+    pub root: Option<String>,
+    pub structs: Vec<StructTemplate>,
+}
+
+impl TemplateFile {
+    /// Loads templates from a file; files with the `.json` extension are JSON and others TOML.
+    ///
+    /// # Arguments
+    /// * `file_name` - the name of the template file.
+    pub fn load(file_name: &str) -> Result<Self, AppError> {
+        let text = std::fs::read_to_string(file_name)?;
+        let is_json = Path::new(file_name)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+        match is_json {
+            true => {
+                serde_json::from_str(&text).map_err(|e| AppError::InvalidTemplate(e.to_string()))
+            }
+            false => toml::from_str(&text).map_err(|e| AppError::InvalidTemplate(e.to_string())),
+        }
+    }
+}
+
+/// A decoded field of a template applied to a file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TemplateField {
+    pub name: String,
+    pub field_type: String,
+    pub offset: u64,
+    pub size: u64,
+    /// The decoded value of a field which isn't a struct or an array.
+    pub value: Option<String>,
+    pub children: Vec<TemplateField>,
+}
+
+/// The type of a field resolved from its type name.
+enum FieldKind<'a> {
+    Number(NumericType),
+    String,
+    Bytes,
+    Struct(&'a StructTemplate),
+}
+
+/// Applies templates to the data of an open file.
+struct TemplateReader<'a> {
+    app_state: &'a AppState,
+    file_index: usize,
+    file_size: u64,
+    structs: HashMap<&'a str, &'a StructTemplate>,
+    fields: usize,
+}
+
+impl<'a> TemplateReader<'a> {
+    fn read(&self, offset: u64, length: usize) -> Result<Vec<u8>, AppError> {
+        let mut buffer = vec![0; length];
+        let count = read_file_data(self.app_state, self.file_index, offset, &mut buffer)?;
+        match count == length {
+            true => Ok(buffer),
+            false => Err(AppError::InvalidData(format!(
+                "The template exceeds the end of the file at {}",
+                offset
+            ))),
+        }
+    }
+
+    fn kind(&self, type_name: &str) -> Result<FieldKind<'a>, AppError> {
+        let number = match type_name {
+            "u8" => NumericType::U8,
+            "u16" => NumericType::U16,
+            "u32" => NumericType::U32,
+            "u64" => NumericType::U64,
+            "u128" => NumericType::U128,
+            "i8" => NumericType::I8,
+            "i16" => NumericType::I16,
+            "i32" => NumericType::I32,
+            "i64" => NumericType::I64,
+            "i128" => NumericType::I128,
+            "f32" => NumericType::F32,
+            "f64" => NumericType::F64,
+            "string" => return Ok(FieldKind::String),
+            "bytes" => return Ok(FieldKind::Bytes),
+            _ => match self.structs.get(type_name) {
+                Some(template) => return Ok(FieldKind::Struct(template)),
+                None => {
+                    return Err(AppError::InvalidTemplate(format!(
+                        "Unknown template type {}",
+                        type_name
+                    )))
+                }
+            },
+        };
+
+        Ok(FieldKind::Number(number))
+    }
+
+    /// Counts a produced field, failing when the template produces too many fields.
+    fn count_field(&mut self) -> Result<(), AppError> {
+        self.fields += 1;
+        match self.fields > MAX_TEMPLATE_FIELDS {
+            true => Err(AppError::InvalidTemplate(
+                "The template produces too many fields".to_string(),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn read_struct(
+        &mut self,
+        template: &'a StructTemplate,
+        name: &str,
+        offset: u64,
+        endian: Endianness,
+        depth: usize,
+    ) -> Result<TemplateField, AppError> {
+        if depth > MAX_TEMPLATE_DEPTH {
+            return Err(AppError::InvalidTemplate(
+                "The templates are nested too deep".to_string(),
+            ));
+        }
+
+        self.count_field()?;
+        let endian = template.endian.unwrap_or(endian);
+        let mut values: HashMap<&str, u64> = HashMap::new();
+        let mut children = Vec::new();
+        let mut pos = offset;
+
+        for field in &template.fields {
+            let kind = self.kind(&field.field_type)?;
+            let length = match &field.length {
+                Some(size) => Some(resolve_size(size, &values)?),
+                None => None,
+            };
+            let endian = field.endian.unwrap_or(endian);
+
+            let child = match &field.count {
+                Some(count) => {
+                    let count = resolve_size(count, &values)?;
+                    self.count_field()?;
+                    let mut items = Vec::new();
+                    let mut item_pos = pos;
+                    for i in 0..count {
+                        let item_name = format!("[{}]", i);
+                        let (item, _) = self.read_field(
+                            &kind,
+                            &field.field_type,
+                            &item_name,
+                            item_pos,
+                            length,
+                            endian,
+                            depth,
+                        )?;
+                        item_pos += item.size;
+                        items.push(item);
+                    }
+
+                    TemplateField {
+                        name: field.name.clone(),
+                        field_type: format!("{}[{}]", field.field_type, count),
+                        offset: pos,
+                        size: item_pos - pos,
+                        value: None,
+                        children: items,
+                    }
+                }
+                None => {
+                    let (child, integer) = self.read_field(
+                        &kind,
+                        &field.field_type,
+                        &field.name,
+                        pos,
+                        length,
+                        endian,
+                        depth,
+                    )?;
+                    if let Some(integer) = integer {
+                        values.insert(&field.name, integer);
+                    }
+
+                    child
+                }
+            };
+
+            pos += child.size;
+            children.push(child);
+        }
+
+        Ok(TemplateField {
+            name: name.to_string(),
+            field_type: template.name.clone(),
+            offset,
+            size: pos - offset,
+            value: None,
+            children,
+        })
+    }
+
+    /// Reads a single field, returning also the value of an unsigned or non-negative integer
+    /// field so later fields can refer to it.
+    #[allow(clippy::too_many_arguments)]
+    fn read_field(
+        &mut self,
+        kind: &FieldKind<'a>,
+        type_name: &str,
+        name: &str,
+        offset: u64,
+        length: Option<u64>,
+        endian: Endianness,
+        depth: usize,
+    ) -> Result<(TemplateField, Option<u64>), AppError> {
+        let (size, value, integer) = match kind {
+            FieldKind::Struct(template) => {
+                return Ok((
+                    self.read_struct(template, name, offset, endian, depth + 1)?,
+                    None,
+                ));
+            }
+            FieldKind::Number(number_type) => {
+                let data = self.read(offset, number_type.size())?;
+                let (value, integer) = decode_number(*number_type, &data, endian);
+                (number_type.size() as u64, value, integer)
+            }
+            FieldKind::String | FieldKind::Bytes => {
+                let length = match length {
+                    Some(length) => length,
+                    None => {
+                        return Err(AppError::InvalidTemplate(format!(
+                            "The field {} has no length",
+                            name
+                        )))
+                    }
+                };
+
+                if offset.saturating_add(length) > self.file_size {
+                    return Err(AppError::InvalidData(format!(
+                        "The template exceeds the end of the file at {}",
+                        offset
+                    )));
+                }
+
+                let max_shown = match kind {
+                    FieldKind::String => MAX_STRING_VALUE_LENGTH,
+                    _ => MAX_BYTES_VALUE_LENGTH,
+                };
+                let shown = (length as usize).min(max_shown);
+                let data = self.read(offset, shown)?;
+
+                let mut value = match kind {
+                    FieldKind::String => String::from_utf8_lossy(&data)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                    _ => data
+                        .iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                };
+
+                if shown < length as usize {
+                    value.push('…');
+                }
+
+                (length, value, None)
+            }
+        };
+
+        self.count_field()?;
+        Ok((
+            TemplateField {
+                name: name.to_string(),
+                field_type: type_name.to_string(),
+                offset,
+                size,
+                value: Some(value),
+                children: Vec::new(),
+            },
+            integer,
+        ))
+    }
+}
+
+/// Gets a size given either as a number or as the name of an earlier integer field.
+fn resolve_size(size: &TemplateSize, values: &HashMap<&str, u64>) -> Result<u64, AppError> {
+    match size {
+        TemplateSize::Fixed(size) => Ok(*size),
+        TemplateSize::Field(name) => match values.get(name.as_str()) {
+            Some(size) => Ok(*size),
+            None => Err(AppError::InvalidTemplate(format!(
+                "Unknown size field {}",
+                name
+            ))),
+        },
+    }
+}
+
+/// Decodes a number in the requested byte order.
+macro_rules! decode {
+    ($type:ty, $data:expr, $endian:expr) => {
+        match $endian {
+            Endianness::Little => <$type>::from_le_bytes($data.try_into().unwrap()),
+            Endianness::Big => <$type>::from_be_bytes($data.try_into().unwrap()),
+        }
+    };
+}
+
+/// Decodes a number, returning its text and its value if it is a non-negative integer.
+fn decode_number(
+    number_type: NumericType,
+    data: &[u8],
+    endian: Endianness,
+) -> (String, Option<u64>) {
+    macro_rules! integer {
+        ($type:ty) => {{
+            let value = decode!($type, data, endian);
+            (value.to_string(), u64::try_from(value).ok())
+        }};
+    }
+
+    match number_type {
+        NumericType::U8 => integer!(u8),
+        NumericType::U16 => integer!(u16),
+        NumericType::U32 => integer!(u32),
+        NumericType::U64 => integer!(u64),
+        NumericType::U128 => integer!(u128),
+        NumericType::I8 => integer!(i8),
+        NumericType::I16 => integer!(i16),
+        NumericType::I32 => integer!(i32),
+        NumericType::I64 => integer!(i64),
+        NumericType::I128 => integer!(i128),
+        NumericType::F32 => (decode!(f32, data, endian).to_string(), None),
+        NumericType::F64 => (decode!(f64, data, endian).to_string(), None),
+    }
+}
+
+/// Applies a struct template to an open file.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file.
+/// * `templates` - the struct templates.
+/// * `offset` - the position to apply the root struct at.
+///
+/// # Returns
+/// The tree of the decoded fields.
+pub fn apply_template(
+    app_state: &AppState,
+    file_index: usize,
+    templates: &TemplateFile,
+    offset: u64,
+) -> Result<TemplateField, AppError> {
+    let root = match &templates.root {
+        Some(root) => templates.structs.iter().find(|s| &s.name == root),
+        None => templates.structs.first(),
+    };
+
+    let root = match root {
+        Some(root) => root,
+        None => {
+            return Err(AppError::InvalidTemplate(
+                "The template has no root struct".to_string(),
+            ))
+        }
+    };
+
+    let mut reader = TemplateReader {
+        app_state,
+        file_index,
+        file_size: file_size(app_state, file_index)?,
+        structs: templates
+            .structs
+            .iter()
+            .map(|s| (s.name.as_str(), s))
+            .collect(),
+        fields: 0,
+    };
+
+    reader.read_struct(root, &root.name, offset, Endianness::Little, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The templates of the `TemplateFile` documentation.
+    const TEMPLATES: &str = r#"
+        root = "Header"
+
+        [[structs]]
+        name = "Header"
+        endian = "little"
+        fields = [
+            { name = "magic", type = "bytes", length = 4 },
+            { name = "count", type = "u16" },
+            { name = "entries", type = "Entry", count = "count" },
+        ]
+
+        [[structs]]
+        name = "Entry"
+        fields = [{ name = "offset", type = "u32", endian = "big" }, { name = "name", type = "string", length = 8 }]
+    "#;
+
+    /// Opens a file with the specified contents in the temporary directory and applies the
+    /// templates to it.
+    fn apply(name: &str, data: &[u8], templates: &str) -> Result<TemplateField, AppError> {
+        let path = std::env::temp_dir().join(format!("hextp-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+
+        let app_state = AppState::default();
+        let file_index =
+            crate::open_file_state(path.to_string_lossy().to_string(), false, &app_state).unwrap();
+        apply_template(
+            &app_state,
+            file_index,
+            &toml::from_str(templates).unwrap(),
+            0,
+        )
+    }
+
+    #[test]
+    fn arrays_are_sized_by_earlier_fields() {
+        let data = b"HXT1\x02\x00\x00\x00\x01\x00first\0\0\0\x00\x00\x02\x00second\0\0trailing";
+        let root = apply("array", data, TEMPLATES).unwrap();
+        assert_eq!((root.offset, root.size), (0, 30));
+        assert_eq!(root.children[0].value.as_deref(), Some("48 58 54 31"));
+        assert_eq!(root.children[1].value.as_deref(), Some("2"));
+
+        let entries = &root.children[2];
+        assert_eq!(entries.field_type, "Entry[2]");
+        assert_eq!((entries.offset, entries.size), (6, 24));
+        let values: Vec<(u64, Option<&str>, Option<&str>)> = entries
+            .children
+            .iter()
+            .map(|entry| {
+                (
+                    entry.offset,
+                    entry.children[0].value.as_deref(),
+                    entry.children[1].value.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (6, Some("256"), Some("first")),
+                (18, Some("512"), Some("second"))
+            ]
+        );
+
+        // A count past the end of the file fails instead of reading beyond it.
+        let data = b"HXT1\x03\x00\x00\x00\x01\x00first\0\0\0";
+        assert!(matches!(
+            apply("short", data, TEMPLATES),
+            Err(AppError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn sizes_refer_only_to_earlier_fields() {
+        let templates = r#"
+            [[structs]]
+            name = "Header"
+            fields = [
+                { name = "name", type = "string", length = "length" },
+                { name = "length", type = "u8" },
+            ]
+        "#;
+        assert!(matches!(
+            apply("order", b"\x04abcd", templates),
+            Err(AppError::InvalidTemplate(_))
+        ));
+    }
+}
@@ -22,25 +22,65 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use std::{fs::File, sync::Mutex};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Mutex,
+    },
+};
 
+use notify::RecommendedWatcher;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    bookmarks::FileBookmarks, edit_journal::EditJournal, error::AppError, file_diff::InsertionDiff,
+    file_source::FileSource, file_type::FileType, file_watch::DiskState,
+    format_parsers::BinaryFormat, tasks::TaskRegistry,
+};
+
+/// The amount of bytes read from a file when the frontend doesn't request a length.
+pub const DEFAULT_READ_LENGTH: usize = 1024;
+
 /// The application file state for the Tauri application.
 pub struct AppFileState {
-    pub file: File,
+    pub file: FileSource,
     pub file_name: String,
-    pub file_index: i32,
+    pub file_index: usize,
+    pub read_write: bool,
     pub file_size: u64,
     pub prev_seek_pos: u64,
-    pub bytes_at_pos: [u8; 1024],
+    pub bytes_at_pos: Vec<u8>,
+    pub journal: EditJournal,
+    pub bookmarks: FileBookmarks,
+    /// The built-in binary format recognised when the file was opened.
+    pub format: Option<BinaryFormat>,
+    /// The type of the file detected when the file was opened.
+    pub file_type: FileType,
+    /// The size and the modification time of the file on disk when it was last read or saved;
+    /// `None` if the file has been removed.
+    pub disk_state: Option<DiskState>,
+    /// The watcher reporting changes made to the file by other processes.
+    pub watcher: Option<RecommendedWatcher>,
+    /// Whether the bytes appended to the file by other processes are added to the view.
+    pub follow: bool,
+    /// Whether the file is being saved; the view can't change until the save has finished.
+    pub saving: bool,
+    /// Counts the changes of the view of the file; results computed from the view stay valid
+    /// while the revision is the same.
+    pub revision: u64,
 }
 
 /// The application default state for the Tauri application.
 impl ::std::default::Default for AppState {
     fn default() -> Self {
         Self {
-            file: Mutex::new(Vec::new()),
+            file: Mutex::new(BTreeMap::new()),
+            next_file_index: AtomicUsize::new(0),
+            max_read_length: AtomicUsize::new(DEFAULT_READ_LENGTH),
+            memory_map_files: AtomicBool::new(false),
+            tasks: TaskRegistry::default(),
+            diff_cache: Mutex::new(None),
         }
     }
 }
@@ -83,7 +123,40 @@ pub struct DataInPosition {
 
 /// The application state for the Tauri application.
 pub struct AppState {
-    pub file: Mutex<Vec<AppFileState>>,
+    /// The open files by their file indices. A file index stays valid until the file is closed
+    /// and is never reused.
+    pub file: Mutex<BTreeMap<usize, AppFileState>>,
+    /// The file index given to the next opened file.
+    pub next_file_index: AtomicUsize,
+    /// The maximum amount of bytes read from a file with a single call.
+    pub max_read_length: AtomicUsize,
+    /// Whether regular files are memory-mapped when opened.
+    pub memory_map_files: AtomicBool,
+    /// The running background tasks.
+    pub tasks: TaskRegistry,
+    /// The last comparison of two files in the insertions mode.
+    pub diff_cache: Mutex<Option<InsertionDiff>>,
+}
+
+impl AppState {
+    /// Runs a function with an open file while holding the lock of the open files.
+    ///
+    /// # Arguments
+    /// * `file_index` - the index of the file.
+    /// * `f` - the function to run with the state of the file.
+    ///
+    /// # Returns
+    /// The result of the function; `AppError::InvalidIndex` if the file index doesn't refer to an open file.
+    pub fn with_file<T, F>(&self, file_index: usize, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&mut AppFileState) -> Result<T, AppError>,
+    {
+        let mut files = self.file.lock()?;
+        match files.get_mut(&file_index) {
+            Some(file_state) => f(file_state),
+            None => Err(AppError::InvalidIndex),
+        }
+    }
 }
 
 /// The application state seriazable result data.
@@ -93,4 +166,8 @@ pub struct AppFileStateResult {
     pub file_name_no_path: String,
     pub file_index: usize,
     pub file_size: u64,
+    pub is_modified: bool,
+    pub format: Option<BinaryFormat>,
+    pub mime_type: String,
+    pub file_type_description: String,
 }
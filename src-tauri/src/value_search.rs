@@ -0,0 +1,335 @@
+/*
+MIT License
+
+Copyright (c) 2024 VPKSoft
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicBool;
+use tauri::Manager;
+
+use crate::{
+    error::AppError,
+    file_scan::{file_size, scan_file},
+    search::{emit_matches, SearchMatch},
+    tasks::TaskContext,
+    types::AppState,
+};
+
+/// The numeric types a value can be searched as.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+}
+
+impl NumericType {
+    /// Gets the size of the type in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            NumericType::U8 | NumericType::I8 => 1,
+            NumericType::U16 | NumericType::I16 => 2,
+            NumericType::U32 | NumericType::I32 | NumericType::F32 => 4,
+            NumericType::U64 | NumericType::I64 | NumericType::F64 => 8,
+            NumericType::U128 | NumericType::I128 => 16,
+        }
+    }
+}
+
+/// Encodes an integer value parsed from a string in the requested byte order.
+macro_rules! integer_bytes {
+    ($value:expr, $type:ty, $big_endian:expr) => {
+        $value
+            .parse::<$type>()
+            .map(|v| match $big_endian {
+                true => v.to_be_bytes().to_vec(),
+                false => v.to_le_bytes().to_vec(),
+            })
+            .map_err(|e| AppError::InvalidArgument(e.to_string()))
+    };
+}
+
+/// A numeric value to search for.
+pub struct NumericValue {
+    value_type: NumericType,
+    big_endian: bool,
+    /// The encoded bytes of an integer value.
+    bytes: Vec<u8>,
+    /// The floating point value to compare to.
+    target: f64,
+    /// The maximum difference of a matching floating point value.
+    tolerance: f64,
+}
+
+impl NumericValue {
+    /// Parses the value to search for. The value is given as a string so 64 and 128-bit
+    /// integers don't lose precision in the frontend.
+    ///
+    /// # Arguments
+    /// * `value` - the decimal value, e.g. `-1234` or `3.14`.
+    /// * `value_type` - the numeric type of the value.
+    /// * `big_endian` - whether the value is stored in the big-endian byte order.
+    /// * `tolerance` - the maximum difference of a matching floating point value.
+    ///
+    /// # Returns
+    /// The value or an error if it can't be represented by the type.
+    pub fn parse(
+        value: &str,
+        value_type: NumericType,
+        big_endian: bool,
+        tolerance: f64,
+    ) -> Result<Self, AppError> {
+        let value = value.trim();
+        let bytes = match value_type {
+            NumericType::U8 => integer_bytes!(value, u8, big_endian)?,
+            NumericType::U16 => integer_bytes!(value, u16, big_endian)?,
+            NumericType::U32 => integer_bytes!(value, u32, big_endian)?,
+            NumericType::U64 => integer_bytes!(value, u64, big_endian)?,
+            NumericType::U128 => integer_bytes!(value, u128, big_endian)?,
+            NumericType::I8 => integer_bytes!(value, i8, big_endian)?,
+            NumericType::I16 => integer_bytes!(value, i16, big_endian)?,
+            NumericType::I32 => integer_bytes!(value, i32, big_endian)?,
+            NumericType::I64 => integer_bytes!(value, i64, big_endian)?,
+            NumericType::I128 => integer_bytes!(value, i128, big_endian)?,
+            NumericType::F32 | NumericType::F64 => Vec::new(),
+        };
+
+        let target = match value_type {
+            // The value is rounded to the type so values like 0.1 are found without a tolerance.
+            NumericType::F32 => value
+                .parse::<f32>()
+                .map_err(|e| AppError::InvalidArgument(e.to_string()))?
+                as f64,
+            NumericType::F64 => value
+                .parse::<f64>()
+                .map_err(|e| AppError::InvalidArgument(e.to_string()))?,
+            _ => 0.0,
+        };
+
+        if !target.is_finite() || !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(AppError::InvalidArgument(
+                "Invalid search value".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            value_type,
+            big_endian,
+            bytes,
+            target,
+            tolerance,
+        })
+    }
+
+    /// Checks whether the value is stored at the start of the data.
+    fn matches_at(&self, data: &[u8]) -> bool {
+        let value = match self.value_type {
+            NumericType::F32 => {
+                let buffer: [u8; 4] = data[..4].try_into().unwrap();
+                match self.big_endian {
+                    true => f32::from_be_bytes(buffer) as f64,
+                    false => f32::from_le_bytes(buffer) as f64,
+                }
+            }
+            NumericType::F64 => {
+                let buffer: [u8; 8] = data[..8].try_into().unwrap();
+                match self.big_endian {
+                    true => f64::from_be_bytes(buffer),
+                    false => f64::from_le_bytes(buffer),
+                }
+            }
+            _ => return data[..self.bytes.len()] == self.bytes[..],
+        };
+
+        (value - self.target).abs() <= self.tolerance
+    }
+}
+
+/// Finds the positions of a numeric value in the whole file chunk by chunk.
+///
+/// # Arguments
+/// * `app_state` - the application state.
+/// * `file_index` - the index of the file to search.
+/// * `value` - the value to search for.
+/// * `aligned` - whether to only check the offsets which are a multiple of the value size.
+/// * `max_results` - the maximum amount of matches to find.
+/// * `cancel` - the cancellation flag checked between the read chunks.
+/// * `on_matches` - called after each read chunk with the matches found in it in ascending
+///   order, the position the file has been searched up to and the size of the file.
+///
+/// # Returns
+/// `Ok(())` if the search completed, was stopped or cancelled; an error otherwise.
+fn scan_value<F>(
+    app_state: &AppState,
+    file_index: usize,
+    value: &NumericValue,
+    aligned: bool,
+    max_results: usize,
+    cancel: &AtomicBool,
+    mut on_matches: F,
+) -> Result<(), AppError>
+where
+    F: FnMut(Vec<SearchMatch>, u64, u64),
+{
+    let total = file_size(app_state, file_index)?;
+    let size = value.value_type.size();
+    let mut found = 0;
+
+    scan_file(app_state, file_index, 0..total, size - 1, cancel, |chunk| {
+        let mut matches = Vec::new();
+        let end = chunk
+            .length
+            .min((chunk.data.len() + 1).saturating_sub(size));
+        let (start, step) = match aligned {
+            true => ((size - (chunk.pos % size as u64) as usize) % size, size),
+            false => (0, 1),
+        };
+
+        for i in (start..end).step_by(step) {
+            if !value.matches_at(&chunk.data[i..]) {
+                continue;
+            }
+
+            if found + matches.len() >= max_results {
+                break;
+            }
+
+            matches.push(SearchMatch {
+                offset: chunk.pos + i as u64,
+                length: size as u64,
+                encoding: None,
+            });
+        }
+
+        found += matches.len();
+        on_matches(matches, chunk.pos + chunk.length as u64, total);
+        found < max_results
+    })?;
+
+    Ok(())
+}
+
+/// Searches the whole file for a numeric value, emitting the matches in batches per read chunk.
+///
+/// # Arguments
+/// * `context` - the context of the search task.
+/// * `file_index` - the index of the file to search.
+/// * `value` - the value to search for.
+/// * `aligned` - whether to only check the offsets which are a multiple of the value size.
+/// * `max_results` - the maximum amount of matches to report.
+///
+/// # Returns
+/// `Ok(())` if the search completed or was cancelled; an error otherwise.
+pub fn search_value(
+    context: &TaskContext,
+    file_index: usize,
+    value: &NumericValue,
+    aligned: bool,
+    max_results: usize,
+) -> Result<(), AppError> {
+    let app_state = context.app.state::<AppState>();
+    scan_value(
+        &app_state,
+        file_index,
+        value,
+        aligned,
+        max_results,
+        context.cancel_flag(),
+        |matches, pos, total| {
+            emit_matches(context, matches);
+            context.progress(pos, total);
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opens a file with the specified contents in the temporary directory and finds the
+    /// positions of a value in it.
+    fn find(name: &str, data: &[u8], value: &NumericValue, aligned: bool) -> Vec<u64> {
+        let path = std::env::temp_dir().join(format!("hexvs-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+
+        let app_state = AppState::default();
+        let file_index =
+            crate::open_file_state(path.to_string_lossy().to_string(), false, &app_state).unwrap();
+        let mut offsets = Vec::new();
+        let cancel = AtomicBool::new(false);
+        scan_value(
+            &app_state,
+            file_index,
+            value,
+            aligned,
+            10,
+            &cancel,
+            |matches, _, _| offsets.extend(matches.iter().map(|m| m.offset)),
+        )
+        .unwrap();
+        offsets
+    }
+
+    #[test]
+    fn floats_match_within_the_tolerance() {
+        let data: Vec<u8> = [1.0f32, 1.05, 2.0, 0.1, 0.95]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+
+        let value = NumericValue::parse("1", NumericType::F32, false, 0.06).unwrap();
+        assert_eq!(find("tolerance", &data, &value, true), vec![0, 4, 16]);
+
+        let value = NumericValue::parse("1", NumericType::F32, false, 0.0).unwrap();
+        assert_eq!(find("exact", &data, &value, true), vec![0]);
+
+        // The value is rounded to the precision of the type.
+        let value = NumericValue::parse("0.1", NumericType::F32, false, 0.0).unwrap();
+        assert_eq!(find("rounded", &data, &value, true), vec![12]);
+
+        assert!(NumericValue::parse("1", NumericType::F64, false, -1.0).is_err());
+        assert!(NumericValue::parse("inf", NumericType::F64, false, 0.0).is_err());
+    }
+
+    #[test]
+    fn aligned_search_skips_unaligned_offsets() {
+        let data = [0x00, 0x02, 0x01, 0x00, 0x02, 0x01, 0x01, 0x02];
+
+        let value = NumericValue::parse("258", NumericType::U16, false, 0.0).unwrap();
+        assert_eq!(find("unaligned", &data, &value, false), vec![1, 4]);
+        assert_eq!(find("aligned", &data, &value, true), vec![4]);
+
+        let value = NumericValue::parse("258", NumericType::U16, true, 0.0).unwrap();
+        assert_eq!(find("big-endian", &data, &value, true), vec![6]);
+        assert!(NumericValue::parse("65536", NumericType::U16, false, 0.0).is_err());
+    }
+}
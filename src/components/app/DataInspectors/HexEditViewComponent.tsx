@@ -34,7 +34,7 @@ import {
     type TextDataInPosition,
     getDataInPosition,
     getTextDataInPosition,
-    readFile,
+    readFileBinary,
 } from "../../../utilities/app/TauriWrappers";
 import { InputHex } from "../Inputs/InputHex";
 import { ByteValueView } from "./ByteValueView";
@@ -213,9 +213,8 @@ const HextEditViewComponent = ({
     }, [fromPosition, hexData, inputsMemo, rows]);
 
     const readFromPosition = React.useCallback(() => {
-        readFile(fileIndex, fromPosition)
-            .then(res => {
-                const buff = Uint8Array.from(atob(res.file_data), c => c.codePointAt(0) ?? 0);
+        readFileBinary(fileIndex, fromPosition)
+            .then(buff => {
                 setHexData([...buff]);
                 readError.current = false;
             })
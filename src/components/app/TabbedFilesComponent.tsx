@@ -43,7 +43,7 @@ export const TabbedFilesComponent = ({
 
         Promise.all(openFileDataPromises)
             .then((res: FileReadResult[]) => {
-                newOpenFileData.push(...res.map(r => ({ ...openFiles[r.file_index], data: r.file_data })));
+                newOpenFileData.push(...res.map((r, i) => ({ ...openFiles[i], data: r.file_data })));
                 setOpenFileData(newOpenFileData);
             })
             .catch((error: Error) => {
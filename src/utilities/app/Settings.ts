@@ -40,6 +40,10 @@ export type Settings = {
     error: boolean;
     /** An error message if one occurred. */
     error_message: string;
+    /** The maximum amount of bytes the frontend can read from a file with a single call. */
+    max_read_length: number;
+    /** A value indicating whether regular files are memory-mapped for faster random access. A mapped file truncated by another process can crash the application. */
+    memory_map_files: boolean;
 };
 
 /**
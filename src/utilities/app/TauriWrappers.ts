@@ -24,17 +24,73 @@ SOFTWARE.
 
 import { invoke } from "@tauri-apps/api/core";
 
+/**
+ * The kinds of the errors returned by the backend commands.
+ */
+type BackendErrorKind =
+    | "not_found"
+    | "permission_denied"
+    | "invalid_index"
+    | "out_of_range"
+    | "read_only"
+    | "busy"
+    | "conflict"
+    | "invalid_argument"
+    | "invalid_data"
+    | "invalid_template"
+    | "io"
+    | "lock_poisoned"
+    | "task";
+
+/**
+ * An error returned by the backend commands.
+ */
+type BackendError = {
+    kind: BackendErrorKind;
+    message: string;
+};
+
+/**
+ * The error thrown by the backend command wrappers. The kind of the error can be used to localise the message.
+ */
+class CommandError extends Error {
+    kind: BackendErrorKind | undefined;
+
+    constructor(error: unknown) {
+        const backendError = error as Partial<BackendError> | undefined;
+        super(backendError?.message ?? `${error}`);
+        this.name = "CommandError";
+        this.kind = backendError?.kind;
+    }
+}
+
 /**
  * Reads bytes from the specified file position specified by the file index.
  * @param {number} fileIndex The index of the file to read.
  * @param {number} filePos The position in the file to start reading.
+ * @param {number} length The amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
  * @returns {Promise<FileReadResult>} The read file content as a base64 encoded string.
  */
-const readFile = async (fileIndex: number, filePos: number) => {
+const readFile = async (fileIndex: number, filePos: number, length?: number) => {
+    try {
+        return (await invoke("read_file", { fileIndex, filePos, length })) as FileReadResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Reads bytes from the specified file position specified by the file index as raw bytes.
+ * @param {number} fileIndex The index of the file to read.
+ * @param {number} filePos The position in the file to start reading.
+ * @param {number} length The amount of bytes to read; limited by the application settings. Defaults to 1024 bytes.
+ * @returns {Promise<Uint8Array>} The read file content.
+ */
+const readFileBinary = async (fileIndex: number, filePos: number, length?: number) => {
     try {
-        return (await invoke("read_file", { fileIndex, filePos })) as FileReadResult;
+        return new Uint8Array((await invoke("read_file_binary", { fileIndex, filePos, length })) as ArrayBuffer);
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
     }
 };
 
@@ -47,7 +103,7 @@ const readFileCurrentPos = async (fileIndex: number) => {
     try {
         return (await invoke("read_file_current_pos", { fileIndex })) as FileReadResult;
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
     }
 };
 
@@ -61,7 +117,48 @@ const openFile = async (fileName: string, readWrite: boolean): Promise<number> =
     try {
         return (await invoke("open_file", { fileName, rw: readWrite })) as number;
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Closes the file specified by the file index. Unsaved changes to the file are discarded.
+ * @param {number} fileIndex The index of the file to close.
+ * @returns {Promise<void>} A promise that resolves when the file is closed.
+ */
+const closeFile = async (fileIndex: number) => {
+    try {
+        await invoke("close_file", { fileIndex });
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Reloads the file specified by the file index from disk after another process has modified it.
+ * @param {number} fileIndex The index of the file to reload.
+ * @param {boolean} discardEdits A value indicating whether unsaved changes to the file are discarded.
+ * @returns {Promise<ReloadResult>} The size of the reloaded file; `conflict` is set and the file is not reloaded if the file has unsaved changes which were not discarded.
+ */
+const reloadFile = async (fileIndex: number, discardEdits?: boolean) => {
+    try {
+        return (await invoke("reload_file", { fileIndex, discardEdits })) as ReloadResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Switches the follow mode of the file specified by the file index. In follow mode the bytes appended to the file by other processes are emitted with the `file-appended` event; each append clears the redo history of the file.
+ * @param {number} fileIndex The index of the file.
+ * @param {boolean} follow A value indicating whether the file is followed.
+ * @returns {Promise<number>} The size of the file.
+ */
+const followFile = async (fileIndex: number, follow: boolean) => {
+    try {
+        return (await invoke("follow_file", { fileIndex, follow })) as number;
+    } catch (error) {
+        throw new CommandError(error);
     }
 };
 
@@ -75,7 +172,7 @@ const getDataInPosition = async (fileIndex: number, filePos: number) => {
     try {
         return (await invoke("get_data_in_position", { fileIndex, filePos })) as DataInPositionResult;
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
     }
 };
 
@@ -125,14 +222,19 @@ type AppFileStateResult = {
     file_index: number;
     file_size: number;
     file_name_no_path: string;
+    is_modified: boolean;
+    format: BinaryFormat | null;
+    mime_type: string;
+    file_type_description: string;
 };
 
 /**
- * The result file data in base64 encoded string and the file index.
+ * The result file data in base64 encoded string, the file index and the amount of bytes read.
  */
 type FileReadResult = {
     file_index: number;
     file_data: string;
+    bytes_read: number;
 };
 
 type TextDataInPosition = {
@@ -155,7 +257,7 @@ const getOpenFiles = async () => {
     try {
         return (await invoke("get_open_files")) as AppFileStateResult[];
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
     }
 };
 
@@ -163,9 +265,713 @@ const getTextDataInPosition = async (fileIndex: number) => {
     try {
         return (await invoke("get_text_data_in_position", { fileIndex })) as TextDataInPosition;
     } catch (error) {
-        throw new Error(`${error}`);
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Overwrites bytes in the edited view of the file specified by the file index.
+ * @param {number} fileIndex The index of the file to edit.
+ * @param {number} filePos The position of the first byte to overwrite.
+ * @param {number[]} data The new bytes.
+ * @returns {Promise<number>} The size of the edited file.
+ */
+const writeBytes = async (fileIndex: number, filePos: number, data: number[]) => {
+    try {
+        return (await invoke("write_bytes", { fileIndex, filePos, data })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Inserts bytes into the edited view of the file specified by the file index.
+ * @param {number} fileIndex The index of the file to edit.
+ * @param {number} filePos The position to insert the bytes at.
+ * @param {number[]} data The bytes to insert.
+ * @returns {Promise<number>} The size of the edited file.
+ */
+const insertBytes = async (fileIndex: number, filePos: number, data: number[]) => {
+    try {
+        return (await invoke("insert_bytes", { fileIndex, filePos, data })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Deletes bytes from the edited view of the file specified by the file index.
+ * @param {number} fileIndex The index of the file to edit.
+ * @param {number} filePos The position of the first byte to delete.
+ * @param {number} length The amount of bytes to delete.
+ * @returns {Promise<number>} The size of the edited file.
+ */
+const deleteBytes = async (fileIndex: number, filePos: number, length: number) => {
+    try {
+        return (await invoke("delete_bytes", { fileIndex, filePos, length })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Saves the edited file specified by the file index over the file on disk.
+ * @param {number} fileIndex The index of the file to save.
+ * @returns {Promise<void>} A promise to save the file.
+ */
+const saveFile = async (fileIndex: number) => {
+    try {
+        await invoke("save_file", { fileIndex });
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Saves the edited file specified by the file index into a new file.
+ * @param {number} fileIndex The index of the file to save.
+ * @param {string} fileName The name of the file to save into.
+ * @returns {Promise<void>} A promise to save the file.
+ */
+const saveFileAs = async (fileIndex: number, fileName: string) => {
+    try {
+        await invoke("save_file_as", { fileIndex, fileName });
+    } catch (error) {
+        throw new CommandError(error);
     }
 };
 
-export { readFile, openFile, readFileCurrentPos, getOpenFiles, getDataInPosition, getTextDataInPosition };
-export type { AppFileStateResult, FileReadResult, DataInPositionResult, TextDataInPosition };
+/**
+ * An undoable edit step in the edit history of a file.
+ */
+type EditHistoryEntry = {
+    description: string;
+    offset: number;
+    length: number;
+};
+
+/**
+ * The undo and redo history of a file.
+ */
+type EditHistory = {
+    undo: EditHistoryEntry[];
+    redo: EditHistoryEntry[];
+};
+
+/**
+ * The size of the edited file and the undone or redone step.
+ */
+type UndoRedoResult = {
+    file_size: number;
+    step: EditHistoryEntry | null;
+};
+
+/**
+ * Undoes the most recent edit step of the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @returns {Promise<UndoRedoResult>} The size of the edited file and the undone step.
+ */
+const undoEdit = async (fileIndex: number) => {
+    try {
+        return (await invoke("undo_edit", { fileIndex })) as UndoRedoResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Redoes the most recently undone edit step of the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @returns {Promise<UndoRedoResult>} The size of the edited file and the redone step.
+ */
+const redoEdit = async (fileIndex: number) => {
+    try {
+        return (await invoke("redo_edit", { fileIndex })) as UndoRedoResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Gets the undo and redo history of the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @returns {Promise<EditHistory>} The undo and redo history of the file.
+ */
+const getEditHistory = async (fileIndex: number) => {
+    try {
+        return (await invoke("get_edit_history", { fileIndex })) as EditHistory;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * The progress of a background task emitted with the `task-progress` event.
+ */
+type TaskProgress = {
+    task_id: number;
+    position: number;
+    total: number;
+};
+
+/**
+ * The state of an open file on disk emitted with the `file-changed` event when another process modifies the file.
+ */
+type FileChanged = {
+    file_index: number;
+    file_size: number;
+    modified: number | null;
+    removed: boolean;
+    is_modified: boolean;
+};
+
+/**
+ * The bytes appended to a followed file emitted with the `file-appended` event. The data is base64 encoded and holds at most the maximum read length of bytes from the end of the file.
+ */
+type FileAppended = {
+    file_index: number;
+    offset: number;
+    file_data: string;
+    bytes_read: number;
+    file_size: number;
+};
+
+/**
+ * The result of reloading a file from disk.
+ */
+type ReloadResult = {
+    file_size: number;
+    conflict: boolean;
+};
+
+/**
+ * The final state of a background task emitted with the `task-finished` event.
+ */
+type TaskFinished = {
+    task_id: number;
+    cancelled: boolean;
+    error: BackendError | null;
+};
+
+/**
+ * The text encodings the text can be searched with.
+ */
+type TextEncoding = "ascii" | "utf8" | "utf16_le" | "utf16_be" | "utf32_le" | "utf32_be";
+
+/**
+ * A match found by a search; the encoding is set for text search matches.
+ */
+type SearchMatch = {
+    offset: number;
+    length: number;
+    encoding: TextEncoding | null;
+};
+
+/**
+ * A batch of matches found by a search task emitted with the `search-matches` event.
+ */
+type SearchMatches = {
+    task_id: number;
+    matches: SearchMatch[];
+};
+
+/**
+ * A byte or text search query.
+ */
+type SearchQuery =
+    | { kind: "bytes"; pattern: string }
+    | { kind: "text"; text: string; encodings: TextEncoding[]; case_sensitive: boolean };
+
+/**
+ * The result of a search and replace; the offsets are the positions of the matches before the replacement.
+ */
+type ReplaceResult = {
+    offsets: number[];
+    file_size: number;
+    dry_run: boolean;
+};
+
+/**
+ * Starts searching the file specified by the file index for a hexadecimal byte pattern such as `4D 5A ?? 00 ?F`.
+ * @param {number} fileIndex The index of the file to search.
+ * @param {string} pattern The hexadecimal pattern to search for; `?` matches any nibble.
+ * @param {number} maxResults The maximum amount of matches to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the search task.
+ */
+const searchBytes = async (fileIndex: number, pattern: string, maxResults?: number) => {
+    try {
+        return (await invoke("search_bytes", { fileIndex, pattern, maxResults })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Starts searching the file specified by the file index for text encoded in one or more text encodings.
+ * @param {number} fileIndex The index of the file to search.
+ * @param {string} text The text to search for.
+ * @param {TextEncoding[]} encodings The encodings to search the text in.
+ * @param {boolean} caseSensitive A value indicating whether the letter case of ASCII letters must match.
+ * @param {number} maxResults The maximum amount of matches to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the search task.
+ */
+const searchText = async (
+    fileIndex: number,
+    text: string,
+    encodings: TextEncoding[],
+    caseSensitive: boolean,
+    maxResults?: number
+) => {
+    try {
+        return (await invoke("search_text", { fileIndex, text, encodings, caseSensitive, maxResults })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Starts searching the file specified by the file index with a byte-oriented regular expression.
+ * @param {number} fileIndex The index of the file to search.
+ * @param {string} pattern The regular expression to search with; `(?-u)` allows matching arbitrary bytes.
+ * @param {number} maxMatchLength The maximum length of a match spanning two read chunks. Defaults to 4096 bytes and is limited to 1 MiB.
+ * @param {number} maxResults The maximum amount of matches to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the search task.
+ */
+const searchRegexBytes = async (fileIndex: number, pattern: string, maxMatchLength?: number, maxResults?: number) => {
+    try {
+        return (await invoke("search_regex_bytes", { fileIndex, pattern, maxMatchLength, maxResults })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * The numeric types a value can be searched as.
+ */
+type NumericType = "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" | "f32" | "f64";
+
+/**
+ * Starts searching the file specified by the file index for a numeric value such as a length field.
+ * @param {number} fileIndex The index of the file to search.
+ * @param {string} value The decimal value to search for; a string keeps 64 and 128-bit integers precise.
+ * @param {NumericType} valueType The numeric type of the value.
+ * @param {boolean} bigEndian A value indicating whether the value is stored in the big-endian byte order.
+ * @param {number} tolerance The maximum difference of a matching floating point value. Defaults to 0.
+ * @param {boolean} aligned A value indicating whether to only check the offsets which are a multiple of the value size.
+ * @param {number} maxResults The maximum amount of matches to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the search task.
+ */
+const searchNumericValue = async (
+    fileIndex: number,
+    value: string,
+    valueType: NumericType,
+    bigEndian: boolean,
+    tolerance?: number,
+    aligned = false,
+    maxResults?: number
+) => {
+    try {
+        return (await invoke("search_numeric_value", {
+            fileIndex,
+            value,
+            valueType,
+            bigEndian,
+            tolerance,
+            aligned,
+            maxResults,
+        })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * A run of printable characters found in a file; the length is in bytes.
+ */
+type ExtractedString = {
+    offset: number;
+    length: number;
+    encoding: TextEncoding;
+    text: string;
+};
+
+/**
+ * A batch of strings found by a strings extraction task emitted with the `strings-found` event.
+ */
+type ExtractedStrings = {
+    task_id: number;
+    strings: ExtractedString[];
+};
+
+/**
+ * Starts extracting the runs of printable characters from the file specified by the file index like `strings(1)`.
+ * @param {number} fileIndex The index of the file to scan.
+ * @param {TextEncoding[]} encodings The encodings to look for strings in.
+ * @param {number} minLength The minimum amount of characters in a string. Defaults to 4.
+ * @param {number} maxResults The maximum amount of strings to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the extraction task.
+ */
+const extractStrings = async (fileIndex: number, encodings: TextEncoding[], minLength?: number, maxResults?: number) => {
+    try {
+        return (await invoke("extract_strings", { fileIndex, encodings, minLength, maxResults })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * How two files are compared; `insertions` detects inserted and deleted bytes in files of different lengths.
+ */
+type DiffMode = "bytes" | "insertions";
+
+/**
+ * A range differing between two files; an inserted or deleted range has the length of zero in the file it is missing from.
+ */
+type DiffRange = {
+    offset_a: number;
+    length_a: number;
+    offset_b: number;
+    length_b: number;
+};
+
+/**
+ * The differing ranges found by a comparison task emitted with the `diff-results` event.
+ */
+type DiffResults = {
+    task_id: number;
+    ranges: DiffRange[];
+};
+
+/**
+ * Starts comparing two open files. The differing ranges are emitted in ascending order with the `diff-results` event.
+ * @param {number} fileIndexA The index of the first file.
+ * @param {number} fileIndexB The index of the second file.
+ * @param {DiffMode} mode How the files are compared.
+ * @param {number} maxResults The maximum amount of differing ranges to report. Defaults to 100 000.
+ * @returns {Promise<number>} The identifier of the comparison task.
+ */
+const diffFiles = async (fileIndexA: number, fileIndexB: number, mode: DiffMode, maxResults?: number) => {
+    try {
+        return (await invoke("diff_files", { fileIndexA, fileIndexB, mode, maxResults })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Finds the next or the previous difference between two open files from a position.
+ * @param {number} fileIndexA The index of the first file.
+ * @param {number} fileIndexB The index of the second file.
+ * @param {DiffMode} mode How the files are compared; in the `insertions` mode the offset is a position of the first file.
+ * @param {number} offset The position to start from; the difference the position is in is skipped.
+ * @param {boolean} backward A value indicating whether to find the previous difference instead of the next one.
+ * @returns {Promise<DiffRange | null>} The differing range or `null` if there are no more differences in the direction.
+ */
+const findDifference = async (fileIndexA: number, fileIndexB: number, mode: DiffMode, offset: number, backward: boolean) => {
+    try {
+        return (await invoke("find_difference", { fileIndexA, fileIndexB, mode, offset, backward })) as DiffRange | null;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * The supported hash and checksum algorithms; `crc16` is CRC-16/ARC and `crc32` is CRC-32/ISO-HDLC.
+ */
+type HashAlgorithm = "crc16" | "crc32" | "adler32" | "md5" | "sha1" | "sha256" | "sha512" | "blake3";
+
+/**
+ * The results of a hash calculation task emitted with the `hash-results` event; the values are lowercase hexadecimal.
+ */
+type HashResults = {
+    task_id: number;
+    start: number;
+    length: number;
+    hashes: { algorithm: HashAlgorithm; value: string }[];
+};
+
+/**
+ * Starts calculating hashes and checksums over a range of the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @param {HashAlgorithm[]} algorithms The algorithms to calculate.
+ * @param {number} start The position of the range. Defaults to the start of the file.
+ * @param {number} length The length of the range. Defaults to the rest of the file.
+ * @returns {Promise<number>} The identifier of the hash calculation task.
+ */
+const calculateHashes = async (fileIndex: number, algorithms: HashAlgorithm[], start?: number, length?: number) => {
+    try {
+        return (await invoke("calculate_hashes", { fileIndex, algorithms, start, length })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * The byte frequencies of a range of a file and the Shannon entropies of the blocks of the whole file in bits per byte,
+ * emitted with the `byte-analysis` event.
+ */
+type ByteAnalysis = {
+    task_id: number;
+    start: number;
+    length: number;
+    histogram: number[];
+    block_size: number;
+    entropy: number[];
+};
+
+/**
+ * Starts calculating the byte histogram of a range of the file specified by the file index and the entropy series of the whole file.
+ * @param {number} fileIndex The index of the file.
+ * @param {number} start The position of the histogram range. Defaults to the start of the file.
+ * @param {number} length The length of the histogram range. Defaults to the rest of the file.
+ * @param {number} blockSize The size of an entropy block. Defaults to 4096 bytes; increased for very large files.
+ * @returns {Promise<number>} The identifier of the analysis task.
+ */
+const analyzeFileBytes = async (fileIndex: number, start?: number, length?: number, blockSize?: number) => {
+    try {
+        return (await invoke("analyze_file_bytes", { fileIndex, start, length, blockSize })) as number;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * A named bookmark or a colored region of a file; the length is zero for a bookmark of a single position.
+ */
+type Bookmark = {
+    id: number;
+    offset: number;
+    length: number;
+    label: string;
+    comment: string;
+    color: string | null;
+};
+
+/**
+ * Gets the bookmarks and the named regions of the file specified by the file index in the order of their positions.
+ * @param {number} fileIndex The index of the file.
+ * @returns {Promise<Bookmark[]>} The bookmarks of the file.
+ */
+const getBookmarks = async (fileIndex: number) => {
+    try {
+        return (await invoke("get_bookmarks", { fileIndex })) as Bookmark[];
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Adds a bookmark or a named region to the file specified by the file index. The bookmarks move along with the bytes they refer to when the file is edited.
+ * @param {number} fileIndex The index of the file.
+ * @param {number} offset The position of the bookmark.
+ * @param {number} length The length of the region; zero for a bookmark of a single position.
+ * @param {string} label The name of the bookmark.
+ * @param {string} comment A free-form comment.
+ * @param {string} color The color of the region as a CSS color value.
+ * @returns {Promise<Bookmark>} The added bookmark.
+ */
+const addBookmark = async (
+    fileIndex: number,
+    offset: number,
+    length: number,
+    label: string,
+    comment?: string,
+    color?: string
+) => {
+    try {
+        return (await invoke("add_bookmark", { fileIndex, offset, length, label, comment, color })) as Bookmark;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Removes a bookmark from the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @param {number} id The identifier of the bookmark.
+ * @returns {Promise<boolean>} A value indicating whether the bookmark existed.
+ */
+const removeBookmark = async (fileIndex: number, id: number) => {
+    try {
+        return (await invoke("remove_bookmark", { fileIndex, id })) as boolean;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Moves the current position of the file specified by the file index to a bookmark and reads the file from there.
+ * @param {number} fileIndex The index of the file.
+ * @param {number} id The identifier of the bookmark.
+ * @param {number} length The amount of bytes to read. Defaults to 1024 bytes.
+ * @returns {Promise<FileReadResult>} The read file content.
+ */
+const jumpToBookmark = async (fileIndex: number, id: number, length?: number) => {
+    try {
+        return (await invoke("jump_to_bookmark", { fileIndex, id, length })) as FileReadResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * A decoded field of a struct template applied to a file; the value is set for fields which aren't structs or arrays.
+ */
+type TemplateField = {
+    name: string;
+    field_type: string;
+    offset: number;
+    size: number;
+    value: string | null;
+    children: TemplateField[];
+};
+
+/**
+ * Applies a struct template defined in a TOML or JSON file to the file specified by the file index.
+ * @param {number} fileIndex The index of the file.
+ * @param {string} templateFile The name of the template file; files with the `.json` extension are JSON and others TOML.
+ * @param {number} offset The position to apply the root struct at.
+ * @returns {Promise<TemplateField>} The tree of the decoded fields.
+ */
+const applyStructTemplate = async (fileIndex: number, templateFile: string, offset: number) => {
+    try {
+        return (await invoke("apply_struct_template", { fileIndex, templateFile, offset })) as TemplateField;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * The built-in binary formats recognised by their magic bytes when a file is opened.
+ */
+type BinaryFormat = "elf" | "pe" | "png" | "zip" | "gzip";
+
+/**
+ * Parses the structure of the file specified by the file index in the built-in binary format recognised when the file was opened.
+ * @param {number} fileIndex The index of the file.
+ * @returns {Promise<TemplateField | null>} The structure tree with the byte ranges of the headers and the data, or `null` if the format isn't recognised. An entry which can't be read, for example in a truncated file, ends its list with a node of the `error` type.
+ */
+const parseFileStructure = async (fileIndex: number) => {
+    try {
+        return (await invoke("parse_file_structure", { fileIndex })) as TemplateField | null;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Replaces the matches of a search in the file specified by the file index as a single undoable edit.
+ * @param {number} fileIndex The index of the file to edit.
+ * @param {SearchQuery} query The byte or text search query.
+ * @param {number[]} replacement The bytes to replace the matches with; an empty array removes the matches.
+ * @param {number[]} offsets The positions of the matches to replace; all matches are replaced if not specified. A position which isn't a match is an error.
+ * @param {boolean} dryRun A value indicating whether to only return the matches which would be replaced.
+ * @param {number} maxResults The maximum amount of matches to replace when the offsets aren't specified. Defaults to 100 000.
+ * @returns {Promise<ReplaceResult>} The positions of the replaced matches and the new file size.
+ */
+const replaceMatches = async (
+    fileIndex: number,
+    query: SearchQuery,
+    replacement: number[],
+    offsets?: number[],
+    dryRun = false,
+    maxResults?: number
+) => {
+    try {
+        return (await invoke("replace_matches", {
+            fileIndex,
+            query,
+            replacement,
+            offsets,
+            dryRun,
+            maxResults,
+        })) as ReplaceResult;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+/**
+ * Requests a running background task such as a search to stop.
+ * @param {number} taskId The identifier of the task to cancel.
+ * @returns {Promise<boolean>} A value indicating whether the task was running.
+ */
+const cancelTask = async (taskId: number) => {
+    try {
+        return (await invoke("cancel_task", { taskId })) as boolean;
+    } catch (error) {
+        throw new CommandError(error);
+    }
+};
+
+export {
+    CommandError,
+    readFile,
+    readFileBinary,
+    openFile,
+    closeFile,
+    reloadFile,
+    followFile,
+    readFileCurrentPos,
+    getOpenFiles,
+    getDataInPosition,
+    getTextDataInPosition,
+    writeBytes,
+    insertBytes,
+    deleteBytes,
+    saveFile,
+    saveFileAs,
+    undoEdit,
+    redoEdit,
+    getEditHistory,
+    searchBytes,
+    searchText,
+    searchRegexBytes,
+    searchNumericValue,
+    extractStrings,
+    diffFiles,
+    findDifference,
+    calculateHashes,
+    analyzeFileBytes,
+    getBookmarks,
+    addBookmark,
+    removeBookmark,
+    jumpToBookmark,
+    applyStructTemplate,
+    parseFileStructure,
+    replaceMatches,
+    cancelTask,
+};
+export type {
+    BackendErrorKind,
+    BackendError,
+    AppFileStateResult,
+    FileReadResult,
+    DataInPositionResult,
+    TextDataInPosition,
+    EditHistoryEntry,
+    EditHistory,
+    UndoRedoResult,
+    TaskProgress,
+    TaskFinished,
+    FileChanged,
+    ReloadResult,
+    FileAppended,
+    SearchMatch,
+    SearchMatches,
+    TextEncoding,
+    SearchQuery,
+    ReplaceResult,
+    NumericType,
+    ExtractedString,
+    ExtractedStrings,
+    DiffMode,
+    DiffRange,
+    DiffResults,
+    HashAlgorithm,
+    HashResults,
+    ByteAnalysis,
+    Bookmark,
+    TemplateField,
+    BinaryFormat,
+};